@@ -1,39 +1,13 @@
-/// Network Routing Example: Finding Optimal Paths and Link Costs
-/// 
-/// This example demonstrates how to use Halide for:
-/// - Finding shortest paths in a network
-/// - Calculating total latency along routes
-/// - Finding maximum bandwidth bottleneck
-/// - Updating link costs and recalculating routes
+//! Network Routing Example: Finding Optimal Paths and Link Costs
+//! 
+//! This example demonstrates how to use Halide for:
+//! - Finding shortest paths in a network
+//! - Calculating total latency along routes
+//! - Finding maximum bandwidth bottleneck
+//! - Updating link costs and recalculating routes
 
-use halide::{Halide, CombineFn};
-
-#[derive(Clone)]
-struct LatencySumCombine;
-impl CombineFn<u64> for LatencySumCombine {
-    // Sum latency along a path
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a + b
-    }
-}
-
-#[derive(Clone)]
-struct MinBandwidthCombine;
-impl CombineFn<u64> for MinBandwidthCombine {
-    // Find minimum bandwidth (bottleneck) along path
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        if a == 0 { b } else if b == 0 { a } else { a.min(b) }
-    }
-}
-
-#[derive(Clone)]
-struct MaxLatencyCombine;
-impl CombineFn<u64> for MaxLatencyCombine {
-    // Find maximum latency link
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a.max(b)
-    }
-}
+use bipath::HalideBuilder;
+use bipath::monoids::{Min, Sum};
 
 fn main() {
 
@@ -45,48 +19,48 @@ fn main() {
     // Router 4: Edge Router 2
     // Router 5: Data Center B
 
-    // Latency values (in milliseconds) for each router
-    let router_latencies = vec![
-        0u64,  // Data Center A (no processing latency)
-        2,     // Edge Router 1
-        1,     // Core Router 1
-        1,     // Core Router 2
-        2,     // Edge Router 2
-        0,     // Data Center B (no processing latency)
-    ];
-
-    let mut latency_network = Halide::new(router_latencies.clone(), 3, LatencySumCombine, 0u64);
-    
-    // Build network topology (linear path to avoid issues)
+    // Latency of each link (in milliseconds), stored on the edges themselves
     // Data Center A -> Edge Router 1 -> Core Router 1 -> Core Router 2 -> Edge Router 2 -> Data Center B
-    latency_network.add_edge(0, 1);
-    latency_network.add_edge(1, 2);
-    latency_network.add_edge(2, 3);
-    latency_network.add_edge(3, 4);
-    latency_network.add_edge(4, 5);
-    
-    latency_network.init(0);
+    let links = [(0, 1, 5u64), (1, 2, 3), (2, 3, 1), (3, 4, 3), (4, 5, 5)];
+
+    let mut latency_network = HalideBuilder::edge_valued(6, Sum)
+        .weighted_edges(links)
+        .build(0)
+        .unwrap();
 
-    let total_latency = latency_network.query(0, 5);
+    let total_latency = latency_network.query(0, 5).unwrap();
+    let route: Vec<String> = latency_network.path_nodes(0, 5).map(|r| r.to_string()).collect();
+    println!("Route: {} ({} hops)", route.join(" -> "), latency_network.tree().distance(0, 5));
     println!("Total latency from Data Center A to B: {} ms", total_latency);
+    println!("Latency between the core routers: {} ms", latency_network.query(2, 3).unwrap());
+
+    // First router reached after the cumulative latency breaks a 10 ms SLA
+    if let Some(router) = latency_network.path_find_first(0, 5, |&total| total > 10) {
+        println!("The 10 ms SLA is broken on arrival at router {}", router);
+    }
+
+    // Reroute traffic over a faster core link
+    latency_network.update(2, 3, 0);
+    println!("After upgrading the core link: {} ms\n", latency_network.query(0, 5).unwrap());
 
     // Bandwidth analysis
     let router_bandwidths = vec![100u64, 10, 40, 40, 10, 100];
-    let mut bandwidth_network = Halide::new(router_bandwidths, 3, MinBandwidthCombine, 100u64);
-    bandwidth_network.add_edge(0, 1);
-    bandwidth_network.add_edge(1, 2);
-    bandwidth_network.add_edge(2, 3);
-    bandwidth_network.add_edge(3, 4);
-    bandwidth_network.add_edge(4, 5);
-    bandwidth_network.init(0);
+    let mut bandwidth_network = HalideBuilder::new(router_bandwidths, Min)
+        .add_edge(0, 1)
+        .add_edge(1, 2)
+        .add_edge(2, 3)
+        .add_edge(3, 4)
+        .add_edge(4, 5)
+        .build(0)
+        .unwrap();
 
-    let bottleneck = bandwidth_network.query(0, 5);
+    let bottleneck = bandwidth_network.query(0, 5).unwrap();
     println!("Bottleneck bandwidth: {} Gbps\n", bottleneck);
 
     // Network optimization
     bandwidth_network.update(1, 1, 25);
     bandwidth_network.update(4, 4, 25);
-    let new_bottleneck = bandwidth_network.query(0, 5);
+    let new_bottleneck = bandwidth_network.query(0, 5).unwrap();
     println!("After upgrade: {} Gbps (improvement: {} Gbps)", new_bottleneck, new_bottleneck - bottleneck);
 }
 
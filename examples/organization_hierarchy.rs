@@ -1,39 +1,15 @@
-/// Organization Hierarchy Example: Company Structure and Reporting
-/// 
-/// This example demonstrates how to use Halide for:
-/// - Finding reporting chains in an organization
-/// - Calculating total salary costs along a department
-/// - Finding maximum authority level in a chain
-/// - Updating employee information and propagating changes
+//! Organization Hierarchy Example: Company Structure and Reporting
+//! 
+//! This example demonstrates how to use Halide for:
+//! - Finding reporting chains in an organization
+//! - Calculating total salary costs along a department
+//! - Totalling salaries across an entire reporting subtree
+//! - Finding maximum authority level in a chain
+//! - Updating employee information and propagating changes
+//! - Hiring new employees without rebuilding the hierarchy
 
-use halide::{Halide, CombineFn};
-
-#[derive(Clone)]
-struct SalarySumCombine;
-impl CombineFn<u64> for SalarySumCombine {
-    // Sum salaries along reporting chain
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a + b
-    }
-}
-
-#[derive(Clone)]
-struct MaxLevelCombine;
-impl CombineFn<u64> for MaxLevelCombine {
-    // Find maximum authority level
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a.max(b)
-    }
-}
-
-#[derive(Clone)]
-struct EmployeeCountCombine;
-impl CombineFn<u64> for EmployeeCountCombine {
-    // Count employees (each node represents 1 employee)
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a + b
-    }
-}
+use bipath::HalideBuilder;
+use bipath::monoids::{Max, Sum};
 
 fn main() {
 
@@ -63,54 +39,52 @@ fn main() {
         70,     // Engineer
     ];
 
-    let mut org_hierarchy = Halide::new(salaries.clone(), 4, SalarySumCombine, 0u64);
-    
-    // Build organizational hierarchy
-    // CEO -> CTO, CFO
-    org_hierarchy.add_edge(0, 1); // CEO -> CTO
-    org_hierarchy.add_edge(0, 2); // CEO -> CFO
-    
-    // CTO -> VP Engineering, VP Product
-    org_hierarchy.add_edge(1, 3); // CTO -> VP Engineering
-    org_hierarchy.add_edge(1, 4); // CTO -> VP Product
-    
-    // CFO -> Director Finance
-    org_hierarchy.add_edge(2, 5); // CFO -> Director Finance
-    
-    // VP Engineering -> Senior Manager Engineering
-    org_hierarchy.add_edge(3, 6); // VP Engineering -> Senior Manager
-    
-    // Senior Manager -> Manager Engineering
-    org_hierarchy.add_edge(6, 7); // Senior Manager -> Manager
-    
-    // Manager -> Senior Engineer, Engineer
-    org_hierarchy.add_edge(7, 8); // Manager -> Senior Engineer
-    org_hierarchy.add_edge(7, 9); // Manager -> Engineer
-    
-    org_hierarchy.init(0);
+    let mut org_hierarchy = HalideBuilder::new(salaries.clone(), Sum)
+        // Build organizational hierarchy
+        // CEO -> CTO, CFO
+        .add_edge(0, 1) // CEO -> CTO
+        .add_edge(0, 2) // CEO -> CFO
+        // CTO -> VP Engineering, VP Product
+        .add_edge(1, 3) // CTO -> VP Engineering
+        .add_edge(1, 4) // CTO -> VP Product
+        // CFO -> Director Finance
+        .add_edge(2, 5) // CFO -> Director Finance
+        // VP Engineering -> Senior Manager Engineering
+        .add_edge(3, 6) // VP Engineering -> Senior Manager
+        // Senior Manager -> Manager Engineering
+        .add_edge(6, 7) // Senior Manager -> Manager
+        // Manager -> Senior Engineer, Engineer
+        .add_edge(7, 8) // Manager -> Senior Engineer
+        .add_edge(7, 9) // Manager -> Engineer
+        .build(0)
+        .unwrap();
 
-    let chain_salary = org_hierarchy.query(9, 0);
+    let chain_salary = org_hierarchy.query(9, 0).unwrap();
     println!("Total salary cost along Engineer->CEO chain: ${}K", chain_salary);
 
-    let engineering_cost = org_hierarchy.query(1, 9);
-    let finance_cost = org_hierarchy.query(2, 5);
-    println!("Engineering dept cost: ${}K, Finance dept cost: ${}K\n", engineering_cost, finance_cost);
+    let engineering_cost = org_hierarchy.query(1, 9).unwrap();
+    let finance_cost = org_hierarchy.query(2, 5).unwrap();
+    println!("Engineering dept cost: ${}K, Finance dept cost: ${}K", engineering_cost, finance_cost);
+
+    let cto_org_cost = org_hierarchy.query_subtree(1);
+    println!("Total salary under the CTO: ${}K\n", cto_org_cost);
 
     // Authority levels
     let authority_levels = vec![10u64, 8, 8, 6, 6, 5, 4, 3, 2, 1];
-    let mut authority_hierarchy = Halide::new(authority_levels.clone(), 4, MaxLevelCombine, 0u64);
-    authority_hierarchy.add_edge(0, 1);
-    authority_hierarchy.add_edge(0, 2);
-    authority_hierarchy.add_edge(1, 3);
-    authority_hierarchy.add_edge(1, 4);
-    authority_hierarchy.add_edge(2, 5);
-    authority_hierarchy.add_edge(3, 6);
-    authority_hierarchy.add_edge(6, 7);
-    authority_hierarchy.add_edge(7, 8);
-    authority_hierarchy.add_edge(7, 9);
-    authority_hierarchy.init(0);
+    let authority_hierarchy = HalideBuilder::new(authority_levels.clone(), Max)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .add_edge(2, 5)
+        .add_edge(3, 6)
+        .add_edge(6, 7)
+        .add_edge(7, 8)
+        .add_edge(7, 9)
+        .build(0)
+        .unwrap();
 
-    let max_authority = authority_hierarchy.query(9, 1);
+    let max_authority = authority_hierarchy.query(9, 1).unwrap();
     println!("Maximum authority in Engineering chain: {}\n", max_authority);
 
     // Salary adjustments
@@ -122,7 +96,17 @@ fn main() {
     org_hierarchy.update(8, 8, (salaries[8] as f64 * 1.1) as u64);
     org_hierarchy.update(9, 9, (salaries[9] as f64 * 1.1) as u64);
     
-    let new_engineering_cost = org_hierarchy.query(1, 9);
+    let new_engineering_cost = org_hierarchy.query(1, 9).unwrap();
     println!("After 10% raise: ${}K (increase: ${}K)", new_engineering_cost, new_engineering_cost - old_engineering_cost);
+
+    // New hires join as leaves under their manager
+    let intern = org_hierarchy.add_leaf(9, 40);
+    let analyst = org_hierarchy.add_leaf(5, 85);
+    println!(
+        "After hiring: Intern->CEO chain ${}K, CFO org ${}K",
+        org_hierarchy.query(intern, 0).unwrap(),
+        org_hierarchy.query_subtree(2)
+    );
+    println!("Analyst reports through: {:?}", org_hierarchy.path_nodes(analyst, 0).collect::<Vec<_>>());
 }
 
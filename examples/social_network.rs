@@ -1,21 +1,14 @@
-/// Social Network Example: Mutual Friends and Friend Recommendations
-/// 
-/// This example demonstrates how to use Halide for:
-/// - Finding mutual friends between two users
-/// - Recommending friends based on connection paths
-/// - Finding the shortest connection path between users
-/// - Analyzing friend network statistics
+//! Social Network Example: Mutual Friends and Friend Recommendations
+//! 
+//! This example demonstrates how to use Halide for:
+//! - Finding mutual friends between two users
+//! - Recommending friends based on connection paths
+//! - Finding the shortest connection path between users
+//! - Analyzing friend network statistics
+//! - Following the network as friendships form and break
 
-use halide::{Halide, CombineFn};
-
-#[derive(Clone)]
-struct FriendSetCombine;
-impl CombineFn<u64> for FriendSetCombine {
-    // Using bitwise OR to combine friend sets (represented as bitmasks)
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a | b
-    }
-}
+use bipath::{HalideBuilder, LinkCutTree};
+use bipath::monoids::Or;
 
 fn main() {
 
@@ -25,30 +18,26 @@ fn main() {
     let mut friend_sets = vec![0u64; n];
     
     // Initialize friend sets (each user is friends with themselves)
-    for i in 0..n {
-        friend_sets[i] = 1u64 << i;
+    for (i, set) in friend_sets.iter_mut().enumerate() {
+        *set = 1u64 << i;
     }
 
     // Create Halide instance for friend set queries
-    let mut network = Halide::new(friend_sets.clone(), 3, FriendSetCombine, 0u64);
-    
-    // Build friendship connections (simpler tree structure)
-    // User 0 is friends with 1, 2
-    network.add_edge(0, 1);
-    network.add_edge(0, 2);
-    
-    // User 1 is friends with 3, 4
-    network.add_edge(1, 3);
-    network.add_edge(1, 4);
-    
-    // User 2 is friends with 5, 6
-    network.add_edge(2, 5);
-    network.add_edge(2, 6);
-    
-    // User 3 is friends with 7
-    network.add_edge(3, 7);
-    
-    network.init(0);
+    let network = HalideBuilder::new(friend_sets.clone(), Or)
+        // Build friendship connections (simpler tree structure)
+        // User 0 is friends with 1, 2
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        // User 1 is friends with 3, 4
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        // User 2 is friends with 5, 6
+        .add_edge(2, 5)
+        .add_edge(2, 6)
+        // User 3 is friends with 7
+        .add_edge(3, 7)
+        .build(0)
+        .unwrap();
 
     // Find mutual friends
     let user3_friends = network.get_node(3).unwrap().value();
@@ -64,4 +53,16 @@ fn main() {
     let depth_lca = tree.get_depth(lca);
     let path_length = depth3 + depth5 - 2 * depth_lca;
     println!("Connection path length between user 3 and 5: {} hops\n", path_length);
+
+    // Friendships change over time, so follow them with a dynamic forest
+    let mut dynamic = LinkCutTree::new(friend_sets, Or);
+    for (u, v) in [(0, 1), (0, 2), (1, 3), (1, 4), (2, 5), (2, 6), (3, 7)] {
+        dynamic.link(u, v).unwrap();
+    }
+
+    // Users 1 and 3 fall out, and user 3 befriends user 6 instead
+    dynamic.cut(1, 3).unwrap();
+    dynamic.link(3, 6).unwrap();
+    let chain = dynamic.query_path(7, 4).unwrap();
+    println!("Users on the chain from user 7 to user 4: {}", chain.count_ones());
 }
@@ -0,0 +1,184 @@
+use std::marker::PhantomData;
+
+use crate::error::HalideError;
+use crate::segment_tree::{CombineFn, DefaultLazyApply, DefaultLazyFunc, FnCombine, LazyApplyFn, LazyFunc};
+use crate::Halide;
+
+/// Collects node values and edges, then builds a fully initialised `Halide`
+///
+/// The topology is validated and frozen by `build`, so a `Halide` can never be
+/// queried before its decomposition exists or see edges added afterwards.
+/// Every method takes and returns the builder by value, so calls chain
+/// straight into `build`.
+///
+/// `U` is the type of the updates the built Halide accepts (see `Halide`).
+pub struct HalideBuilder<T, C, LA = DefaultLazyApply, LF = DefaultLazyFunc, U = T> {
+    values: Vec<T>,
+    edges: Vec<(usize, usize)>,
+    weights: Vec<(usize, usize, T)>,
+    edge_valued: bool,
+    combine_fn: C,
+    lazy_apply_fn: LA,
+    lazy_func: LF,
+    update: PhantomData<U>,
+}
+
+impl<T, C> HalideBuilder<T, C>
+where
+    T: Clone,
+    C: CombineFn<T> + Clone,
+{
+    /// Start building a Halide whose updates assign values
+    ///
+    /// # Arguments
+    /// * `values` - Initial values for each node (index corresponds to node id)
+    /// * `combine_fn` - Function to combine two segment tree values (see `monoids`)
+    pub fn new(values: Vec<T>, combine_fn: C) -> Self {
+        Self::with_lazy(values, combine_fn, DefaultLazyApply, DefaultLazyFunc)
+    }
+
+    /// Start building an edge-valued Halide over `n` nodes, whose values are
+    /// given by `add_weighted_edge`
+    pub fn edge_valued(n: usize, combine_fn: C) -> Self {
+        Self::new(vec![combine_fn.identity(); n], combine_fn).values_on_edges()
+    }
+}
+
+impl<T, F> HalideBuilder<T, FnCombine<F, T>>
+where
+    T: Clone,
+    F: Fn(&T, &T) -> T + Clone,
+{
+    /// Start building a Halide combining values with a closure
+    ///
+    /// # Arguments
+    /// * `values` - Initial values for each node (index corresponds to node id)
+    /// * `f` - Associative function combining two values
+    /// * `identity` - Identity element of `f`
+    pub fn from_fn(values: Vec<T>, f: F, identity: T) -> Self {
+        Self::new(values, FnCombine::new(f, identity))
+    }
+}
+
+impl<T, C, LA, LF, U> HalideBuilder<T, C, LA, LF, U>
+where
+    T: Clone,
+    U: Clone,
+    C: CombineFn<T> + Clone,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
+{
+    /// Start building a Halide with custom update semantics
+    ///
+    /// # Arguments
+    /// * `values` - Initial values for each node (index corresponds to node id)
+    /// * `combine_fn` - Function to combine two segment tree values (see `monoids`)
+    /// * `lazy_apply_fn` - Composes two pending updates into one
+    /// * `lazy_func` - Applies a pending update to a segment aggregate
+    pub fn with_lazy(values: Vec<T>, combine_fn: C, lazy_apply_fn: LA, lazy_func: LF) -> Self {
+        Self {
+            values,
+            edges: Vec::new(),
+            weights: Vec::new(),
+            edge_valued: false,
+            combine_fn,
+            lazy_apply_fn,
+            lazy_func,
+            update: PhantomData,
+        }
+    }
+
+    /// Make the Halide edge-valued even if no weighted edge is added
+    ///
+    /// Each node's value then stands for the edge to its parent, as
+    /// described in `add_weighted_edge`, whatever the update semantics.
+    pub fn values_on_edges(mut self) -> Self {
+        self.edge_valued = true;
+        self
+    }
+
+    /// Add an undirected edge between nodes u and v
+    pub fn add_edge(mut self, u: usize, v: usize) -> Self {
+        self.edges.push((u, v));
+        self
+    }
+
+    /// Add an undirected edge between u and v carrying the value `w`
+    ///
+    /// This makes the Halide edge-valued: once rooted, `w` is stored on
+    /// whichever of u and v is the child, the root holds the identity, and
+    /// path queries and updates leave out the LCA's slot. Any edges added
+    /// without a weight keep their child's initial value.
+    pub fn add_weighted_edge(mut self, u: usize, v: usize, w: T) -> Self {
+        self.edges.push((u, v));
+        self.weights.push((u, v, w));
+        self.edge_valued = true;
+        self
+    }
+
+    /// Add every edge of a weighted edge list (see `add_weighted_edge`)
+    pub fn weighted_edges<I>(mut self, edges: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, usize, T)>,
+    {
+        for (u, v, w) in edges {
+            self = self.add_weighted_edge(u, v, w);
+        }
+        self
+    }
+
+    /// Add every edge of an edge list
+    pub fn edges<I>(mut self, edges: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        self.edges.extend(edges);
+        self
+    }
+
+    /// Add an edge from every node to its parent, where `parents[v]` is
+    /// `None` for the root
+    pub fn parents(mut self, parents: &[Option<usize>]) -> Self {
+        let edges = parents
+            .iter()
+            .enumerate()
+            .filter_map(|(v, p)| p.map(|p| (p, v)));
+        self.edges.extend(edges);
+        self
+    }
+
+    /// Validate the edges and decompose the tree rooted at `root`
+    ///
+    /// Fails if there are no nodes, an id is out of range, or the edges
+    /// don't form a single tree (self-loops, duplicates, cycles, or nodes
+    /// unreachable from `root`).
+    pub fn build(self, root: usize) -> Result<Halide<T, C, LA, LF, U>, HalideError> {
+        let (mut halide, weights) = self.with_edges()?;
+        halide.try_init(root, weights)?;
+        Ok(halide)
+    }
+
+    /// Validate the edges and decompose every tree of a forest
+    ///
+    /// Each tree is rooted at its smallest node id (see `Tree::roots`). Nodes
+    /// in different trees have no path between them, so path queries on
+    /// them return `None`. Fails on the same errors as `build`, except that
+    /// the nodes needn't all be connected.
+    pub fn build_forest(self) -> Result<Halide<T, C, LA, LF, U>, HalideError> {
+        let (mut halide, weights) = self.with_edges()?;
+        halide.try_init_forest(weights)?;
+        Ok(halide)
+    }
+
+    /// A Halide holding the values and edges, plus the edge weights still to
+    /// be placed once it is rooted
+    #[allow(clippy::type_complexity)]
+    fn with_edges(self) -> Result<(Halide<T, C, LA, LF, U>, Vec<(usize, usize, T)>), HalideError> {
+        let mut halide = Halide::with_lazy(self.values, self.combine_fn, self.lazy_apply_fn, self.lazy_func);
+        halide.edge_valued = self.edge_valued;
+        for (u, v) in self.edges {
+            halide.try_add_edge(u, v)?;
+        }
+        Ok((halide, self.weights))
+    }
+}
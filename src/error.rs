@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Errors reported by the fallible (`try_*`) API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HalideError {
+    /// A node id outside `0..n`
+    InvalidNode { id: usize, n: usize },
+    /// An edge from a node to itself
+    SelfLoop(usize),
+    /// The same edge was added more than once
+    DuplicateEdge(usize, usize),
+    /// The edge closes a cycle, so the input isn't a tree
+    Cycle(usize, usize),
+    /// Only `reached` of the `n` nodes are connected to the root
+    Disconnected { reached: usize, n: usize },
+    /// The tree has no nodes
+    EmptyTree,
+    /// The two nodes are in different trees of a forest
+    DifferentTrees(usize, usize),
+    /// There is no edge between the two nodes to cut
+    MissingEdge(usize, usize),
+    /// The node is a root of an edge-valued tree, so there is no edge above
+    /// it to hold a value
+    RootHasNoEdge(usize),
+}
+
+impl fmt::Display for HalideError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HalideError::InvalidNode { id, n } => write!(f, "node {} is out of range for a tree of {} nodes", id, n),
+            HalideError::SelfLoop(v) => write!(f, "edge ({}, {}) is a self-loop", v, v),
+            HalideError::DuplicateEdge(u, v) => write!(f, "edge ({}, {}) was added more than once", u, v),
+            HalideError::Cycle(u, v) => write!(f, "edge ({}, {}) closes a cycle", u, v),
+            HalideError::Disconnected { reached, n } => {
+                write!(f, "only {} of {} nodes are connected to the root", reached, n)
+            }
+            HalideError::EmptyTree => write!(f, "the tree has no nodes"),
+            HalideError::DifferentTrees(u, v) => write!(f, "nodes {} and {} are in different trees", u, v),
+            HalideError::MissingEdge(u, v) => write!(f, "there is no edge ({}, {})", u, v),
+            HalideError::RootHasNoEdge(v) => write!(f, "node {} is a root, so it has no edge value", v),
+        }
+    }
+}
+
+impl std::error::Error for HalideError {}
@@ -1,276 +1,878 @@
+pub mod builder;
+pub mod error;
+pub mod link_cut;
+pub mod monoids;
 pub mod node;
+pub mod path;
 pub mod segment_tree;
 pub mod tree;
 
-pub use segment_tree::CombineFn;
+pub use segment_tree::{CombineFn, FnCombine, LazyApplyFn, LazyFunc, DefaultLazyApply, DefaultLazyFunc};
+pub use segment_tree::{FromLen, RangeAddSum, RangeAddMinMax, RangeAssignSum, RangeAssignMinMax, RangeAffineSum};
+pub use builder::HalideBuilder;
+pub use error::HalideError;
 pub use node::Node;
+pub use link_cut::LinkCutTree;
+pub use path::{Direction, PathAggregate};
 pub use tree::Tree;
-use segment_tree::{SegmentTree, DefaultLazyApply, DefaultLazyFunc};
+use segment_tree::SegmentTree;
 
 /// Heavy-Light Decomposition structure for tree path queries and updates
-pub struct Halide<T, C>
+///
+/// Built with `HalideBuilder`, so the topology is fixed and fully decomposed
+/// before any query can be made. The topology may also be a forest, in which
+/// case path queries between different trees return `None`.
+///
+/// `LA` and `LF` decide what an update does: the defaults assign the new
+/// value, while custom implementations can add, multiply, take minimums, etc.
+/// Updates have type `U`, which is the value type unless `LF` applies some
+/// other type (e.g. an `i64` delta to a `(sum, min, count)` aggregate).
+///
+/// Values normally live on nodes. A Halide built from weighted edges (see
+/// `HalideBuilder::add_weighted_edge`) is edge-valued instead: each edge's
+/// value is stored on its child node, the root holds the identity, and path
+/// operations skip the LCA so only the edges between u and v are touched.
+///
+/// Queries take `&self`, so a `Halide` can be shared between reader threads
+/// (it is `Sync` whenever its value and function types are).
+///
+/// New leaves can be attached with `add_leaf`. Each one becomes a heavy chain
+/// of its own in a spare label slot, and once about √n slots have been used
+/// the whole tree is decomposed again, so growing the tree costs amortised
+/// O(√n log n) per leaf while every query stays correct in between.
+pub struct Halide<T, C, LA = DefaultLazyApply, LF = DefaultLazyFunc, U = T>
 where
-    T: Clone + Copy + Default + PartialEq,
+    T: Clone,
+    U: Clone,
     C: CombineFn<T>,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
 {
     tree: Tree<T>,
     bigchild: Vec<Option<usize>>,
     sz: Vec<usize>,
     chain: Vec<usize>,
     label: Vec<usize>,
-    label_time: usize,
-    seg_tree: SegmentTree<T, C, DefaultLazyApply, DefaultLazyFunc>,
+    node_at_label: Vec<usize>,
+    seg_tree: SegmentTree<T, U, C, LA, LF>,
     combine_fn: C,
     sentinel: T,
+    edge_valued: bool,
+    decomposed: usize,
+    capacity: usize,
 }
 
-impl<T, C> Halide<T, C>
+/// Label slots kept free for `add_leaf` after decomposing n nodes
+fn spare_labels(n: usize) -> usize {
+    n.isqrt() + 1
+}
+
+impl<T, C, LA, LF, U> Halide<T, C, LA, LF, U>
 where
-    T: Clone + Copy + Default + PartialEq,
+    T: Clone,
+    U: Clone,
     C: CombineFn<T> + Clone,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
 {
-    /// Create a new Halide instance
-    /// 
-    /// # Arguments
-    /// * `values` - Initial values for each node (index corresponds to node id)
-    /// * `lg` - Logarithm base 2 of maximum depth (for binary lifting)
-    /// * `combine_fn` - Function to combine two segment tree values
-    /// * `sentinel` - Sentinel value for segment tree queries (identity element for combine)
-    pub fn new(values: Vec<T>, lg: usize, combine_fn: C, sentinel: T) -> Self {
+    /// Create an instance with no edges; `HalideBuilder::build` adds them and calls `init`
+    pub(crate) fn with_lazy(values: Vec<T>, combine_fn: C, lazy_apply_fn: LA, lazy_func: LF) -> Self {
         let n = values.len();
-        let tree = Tree::new(n, values, lg);
-        
-        let lazy_apply = DefaultLazyApply;
-        let lazy_func = DefaultLazyFunc;
-        let lazy_sentinel = None;
-        let seg_tree = SegmentTree::new(n, combine_fn.clone(), lazy_apply, lazy_func, sentinel, lazy_sentinel);
-        
+        let tree = Tree::new(n, values);
+
+        let sentinel = combine_fn.identity();
+        // Sized for every label up front; `init` fills it in one pass once
+        // every node has its label
+        let seg_tree = SegmentTree::new(n + spare_labels(n), combine_fn.clone(), lazy_apply_fn, lazy_func);
+
         Self {
             tree,
             bigchild: vec![None; n],
             sz: vec![0; n],
             chain: (0..n).collect(),
             label: vec![0; n],
-            label_time: 0,
+            node_at_label: vec![0; n],
             seg_tree,
             combine_fn,
             sentinel,
+            edge_valued: false,
+            decomposed: 0,
+            capacity: 0,
         }
     }
 
     /// Add an undirected edge between nodes u and v
-    pub fn add_edge(&mut self, u: usize, v: usize) {
+    pub(crate) fn add_edge(&mut self, u: usize, v: usize) {
         self.tree.add_edge(u, v);
     }
 
-    /// Initialize the tree structure (call after adding all edges)
-    /// 
+    /// Add an undirected edge, rejecting out-of-range ids and self-loops
+    pub(crate) fn try_add_edge(&mut self, u: usize, v: usize) -> Result<(), HalideError> {
+        self.tree.check_node(u)?;
+        self.tree.check_node(v)?;
+        if u == v {
+            return Err(HalideError::SelfLoop(u));
+        }
+        self.add_edge(u, v);
+        Ok(())
+    }
+
+    /// Initialize the tree structure, first checking that the edges form a tree
+    ///
+    /// Duplicate edges, cycles and nodes unreachable from `root` are reported
+    /// instead of producing a broken decomposition.
+    ///
     /// # Arguments
-    /// * `root` - Root node index (default: 0)
-    pub fn init(&mut self, root: usize) {
+    /// * `root` - Root node index
+    /// * `edge_weights` - `(u, v, w)` triples for an edge-valued tree, empty otherwise
+    pub(crate) fn try_init(&mut self, root: usize, edge_weights: Vec<(usize, usize, T)>) -> Result<(), HalideError> {
+        if self.tree.is_empty() {
+            return Err(HalideError::EmptyTree);
+        }
+        self.tree.check_node(root)?;
+        self.tree.validate(root)?;
+        self.init(&[root], edge_weights);
+        Ok(())
+    }
+
+    /// Initialize a forest, rooting every tree at its smallest node id
+    ///
+    /// Like `try_init`, but nodes needn't all be connected.
+    pub(crate) fn try_init_forest(&mut self, edge_weights: Vec<(usize, usize, T)>) -> Result<(), HalideError> {
+        if self.tree.is_empty() {
+            return Err(HalideError::EmptyTree);
+        }
+        let roots = self.tree.validate_forest()?;
+        self.init(&roots, edge_weights);
+        Ok(())
+    }
+
+    /// Initialize the tree structure (call after adding all edges)
+    fn init(&mut self, roots: &[usize], edge_weights: Vec<(usize, usize, T)>) {
+        self.decompose(roots);
+
+        // Once the tree is rooted, each edge's value moves onto its child
+        if self.edge_valued {
+            for (u, v, w) in edge_weights {
+                let child = if self.tree.get_parent(v) == Some(u) { v } else { u };
+                self.tree.get_node_mut(child).unwrap().set_value(w);
+            }
+            for &root in roots {
+                self.tree.get_node_mut(root).unwrap().set_value(self.sentinel.clone());
+            }
+        }
+
+        let by_label = self
+            .node_at_label
+            .iter()
+            .map(|&v| self.tree.get_node(v).unwrap().value().clone())
+            .collect();
+        self.load(by_label);
+    }
+
+    /// Build the segment tree from values in label order, followed by the
+    /// spare slots `add_leaf` fills in
+    ///
+    /// `init` and `rebuild` both come through here, and `assign` is the same
+    /// O(n) bottom-up build `SegmentTree::from_slice` uses.
+    fn load(&mut self, mut by_label: Vec<T>) {
+        let n = by_label.len();
+        self.capacity = n + spare_labels(n);
+        by_label.resize(self.capacity, self.sentinel.clone());
+        self.seg_tree.assign(&by_label);
+    }
+
+    /// Root the tree and label every node, leaving the segment tree to the caller
+    fn decompose(&mut self, roots: &[usize]) {
         // Initialize tree (builds LCA structure, depth, parent)
-        self.tree.init(root);
+        self.tree.init(roots);
 
         // Compute subtree sizes and identify heavy children
-        self.dfs_size(root, None);
+        self.dfs_size();
 
         // Compute chains
-        self.dfs_chains(root, None);
-
-        // Label nodes and initialize segment tree
-        self.label_time = 0;
-        self.dfs_labels(root, None);
-    }
-
-    fn dfs_size(&mut self, v: usize, p: Option<usize>) {
-        self.sz[v] = 1;
-        let mut bigc = None;
-        let mut bigv = 0;
-
-        let edges_v = self.tree.get_edges(v).clone();
-        for x in edges_v {
-            if Some(x) != p {
-                self.dfs_size(x, Some(v));
-                self.sz[v] += self.sz[x];
-                if self.sz[x] > bigv {
-                    bigc = Some(x);
-                    bigv = self.sz[x];
+        self.dfs_chains();
+
+        // Label nodes; each tree of a forest takes the next block of labels
+        let mut label_time = 0;
+        for &root in roots {
+            label_time = self.dfs_labels(root, label_time);
+        }
+        self.decomposed = self.tree.len();
+    }
+
+    /// Append a new leaf below `parent` holding `value`, returning its id
+    ///
+    /// In an edge-valued tree `value` belongs to the new edge from `parent`.
+    /// The leaf takes a spare label as a heavy chain of its own, so it is
+    /// visible to every query straight away; when no spare label is left the
+    /// tree is decomposed again first (see `rebuild`).
+    ///
+    /// # Panics
+    /// If `parent` is out of range; `try_add_leaf` reports this as an error
+    /// instead.
+    pub fn add_leaf(&mut self, parent: usize, value: T) -> usize {
+        if self.node_at_label.len() == self.capacity {
+            self.rebuild();
+        }
+
+        let v = self.tree.add_leaf(parent, value.clone());
+        let l = self.node_at_label.len();
+        self.bigchild.push(None);
+        self.sz.push(1);
+        self.chain.push(v);
+        self.label.push(l);
+        self.node_at_label.push(v);
+        self.seg_tree.point_set(l, value);
+        v
+    }
+
+    /// Append a new leaf below `parent`, checking its id first
+    pub fn try_add_leaf(&mut self, parent: usize, value: T) -> Result<usize, HalideError> {
+        self.check_nodes(&[parent])?;
+        Ok(self.add_leaf(parent, value))
+    }
+
+    /// Decompose the tree again, placing every leaf added since the last
+    /// decomposition on a proper heavy chain
+    ///
+    /// Current values, including pending updates, carry over. `add_leaf`
+    /// calls this on its own; calling it earlier just makes the following
+    /// queries cheaper. Takes O(n log n).
+    pub fn rebuild(&mut self) {
+        let n = self.tree.len();
+        let mut values: Vec<Option<T>> = (0..n).map(|v| Some(self.get(v))).collect();
+        let roots = self.tree.roots().to_vec();
+
+        self.bigchild = vec![None; n];
+        self.sz = vec![0; n];
+        self.chain = (0..n).collect();
+        self.label = vec![0; n];
+        self.node_at_label = vec![0; n];
+        self.decompose(&roots);
+
+        let by_label = self.node_at_label.iter().map(|&v| values[v].take().unwrap()).collect();
+        self.load(by_label);
+    }
+
+    /// Subtree sizes and heavy children, visiting children before parents
+    fn dfs_size(&mut self) {
+        let par = self.tree.par();
+        for &v in self.tree.order().iter().rev() {
+            self.sz[v] = 1;
+            let mut bigc = None;
+            let mut bigv = 0;
+
+            for &x in self.tree.get_edges(v) {
+                if Some(x) != par[v] {
+                    self.sz[v] += self.sz[x];
+                    if self.sz[x] > bigv {
+                        bigc = Some(x);
+                        bigv = self.sz[x];
+                    }
                 }
             }
-        }
 
-        self.bigchild[v] = bigc;
+            self.bigchild[v] = bigc;
+        }
     }
 
-    fn dfs_chains(&mut self, v: usize, p: Option<usize>) {
-        if let Some(bc) = self.bigchild[v] {
-            self.chain[bc] = self.chain[v];
+    /// Chain heads, visiting parents before children
+    fn dfs_chains(&mut self) {
+        for &v in self.tree.order() {
+            if let Some(bc) = self.bigchild[v] {
+                self.chain[bc] = self.chain[v];
+            }
         }
+    }
+
+    /// Heavy-first DFS labels starting from `label_time`, using an explicit
+    /// stack; returns the first label left unused
+    fn dfs_labels(&mut self, root: usize, mut label_time: usize) -> usize {
+        let par = self.tree.par();
+        let mut stack = vec![root];
+
+        while let Some(v) = stack.pop() {
+            self.label[v] = label_time;
+            self.node_at_label[label_time] = v;
+            label_time += 1;
+
+            // Light children are pushed in reverse so they pop in edge order,
+            // after the heavy child's whole subtree has been labelled
+            for &x in self.tree.get_edges(v).iter().rev() {
+                if Some(x) != par[v] && Some(x) != self.bigchild[v] {
+                    stack.push(x);
+                }
+            }
 
-        let edges_v = self.tree.get_edges(v).clone();
-        for x in edges_v {
-            if Some(x) != p {
-                self.dfs_chains(x, Some(v));
+            if let Some(bc) = self.bigchild[v] {
+                stack.push(bc);
             }
         }
+
+        label_time
     }
 
-    fn dfs_labels(&mut self, v: usize, p: Option<usize>) {
-        self.label[v] = self.label_time;
-        self.label_time += 1;
-        
-        if let Some(node) = self.tree.get_node(v) {
-            self.seg_tree.point_update(self.label[v], *node.value());
+    /// Split the path from u to v into contiguous label ranges, in u -> v order
+    ///
+    /// Each piece is `(lo, hi, direction)` with `lo <= hi`: `Direction::Up`
+    /// pieces are walked from label `hi` down to `lo` (towards the root), and
+    /// `Direction::Down` pieces from `lo` up to `hi`. Any array indexed by
+    /// `get_label` can be queried or updated over the same decomposition.
+    ///
+    /// Right after a decomposition a path crosses O(log n) heavy chains, so
+    /// there are O(log n) pieces. A leaf added by `add_leaf` since then is a
+    /// chain of its own and adds a single-label piece, so in general there
+    /// are O(log n + k) pieces for k such leaves on the path (k stays below
+    /// about √n, since the tree is decomposed again after that many).
+    ///
+    /// In an edge-valued tree the LCA's slot is left out, so the pieces cover
+    /// exactly the edges of the path (and none at all when u == v).
+    ///
+    /// # Panics
+    /// If u and v are in different trees of a forest.
+    pub fn path_segments(&self, u: usize, v: usize) -> impl Iterator<Item = (usize, usize, Direction)> {
+        self.chain_segments(u, v, self.edge_valued)
+    }
+
+    /// Nodes on the path from u to v, in order and including both ends
+    ///
+    /// The path has `tree().distance(u, v) + 1` nodes. Edge-valued trees list
+    /// the LCA as well, since it is still a node of the path.
+    ///
+    /// # Panics
+    /// If u and v are in different trees of a forest.
+    pub fn path_nodes(&self, u: usize, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.chain_segments(u, v, false)
+            .flat_map(|(lo, hi, direction)| {
+                (0..=hi - lo).map(move |i| match direction {
+                    Direction::Up => hi - i,
+                    Direction::Down => lo + i,
+                })
+            })
+            .map(move |l| self.node_at_label[l])
+    }
+
+    /// The k-th ancestor of v (v itself for k = 0), or `None` if v is less
+    /// than k deep
+    ///
+    /// Walks up whole heavy chains and finishes with a label lookup, so this
+    /// takes O(log n) without touching the binary lifting table, plus one
+    /// step per leaf added by `add_leaf` since the last decomposition that it
+    /// passes.
+    pub fn kth_ancestor(&self, mut v: usize, mut k: usize) -> Option<usize> {
+        let depth = self.tree.depth();
+        let par = self.tree.par();
+
+        loop {
+            let top = self.chain[v];
+            let climb = depth[v] - depth[top];
+            if k <= climb {
+                // A chain occupies consecutive labels, deepest last
+                return Some(self.node_at_label[self.label[v] - k]);
+            }
+            k -= climb + 1;
+            v = par[top]?;
         }
+    }
 
-        if let Some(bc) = self.bigchild[v] {
-            self.dfs_labels(bc, Some(v));
+    /// The k-th node on the path from u to v (u itself for k = 0), or `None`
+    /// if the path has fewer than k edges or there is no path
+    pub fn kth_on_path(&self, u: usize, v: usize, k: usize) -> Option<usize> {
+        if !self.tree.connected(u, v) {
+            return None;
         }
+        let l = self.tree.lca(u, v);
+        let up = self.tree.get_depth(u) - self.tree.get_depth(l);
+        let down = self.tree.get_depth(v) - self.tree.get_depth(l);
 
-        let edges_v = self.tree.get_edges(v).clone();
-        for x in edges_v {
-            if Some(x) != p && Some(x) != self.bigchild[v] {
-                self.dfs_labels(x, Some(v));
-            }
+        if k <= up {
+            self.kth_ancestor(u, k)
+        } else if k <= up + down {
+            self.kth_ancestor(v, up + down - k)
+        } else {
+            None
         }
     }
 
-    /// Query a chain from v to p (excludes p)
-    fn query_chain(&mut self, mut v: usize, p: usize) -> T {
-        let mut val = self.sentinel;
+    /// The pieces behind `path_segments`, optionally leaving out the LCA's slot
+    fn chain_segments(
+        &self,
+        mut u: usize,
+        mut v: usize,
+        skip_lca: bool,
+    ) -> impl Iterator<Item = (usize, usize, Direction)> {
         let depth = self.tree.depth();
         let par = self.tree.par();
-        
-        while depth[p] < depth[v] {
-            let mut top = self.chain[v];
-            if depth[top] <= depth[p] {
-                let diff = depth[v] - depth[p];
-                if diff > 0 {
-                    top = self.tree.get_kth_ancestor(v, diff - 1);
-                    if top == usize::MAX {
-                        break;
-                    }
-                } else {
-                    break;
-                }
-            }
-            val = self.combine_fn.combine(val, self.seg_tree.query(self.label[top], self.label[v]));
-            if let Some(parent) = par[top] {
-                v = parent;
+
+        // The v side is discovered from v upwards, so it is reversed at the end
+        let mut up = Vec::new();
+        let mut down = Vec::new();
+
+        while self.chain[u] != self.chain[v] {
+            let (top_u, top_v) = (self.chain[u], self.chain[v]);
+            if depth[top_u] >= depth[top_v] {
+                up.push((self.label[top_u], self.label[u], Direction::Up));
+                u = par[top_u].expect("u and v must be in the same tree");
             } else {
-                break;
+                down.push((self.label[top_v], self.label[v], Direction::Down));
+                v = par[top_v].expect("u and v must be in the same tree");
             }
         }
-        
-        val
+
+        // u and v now share a chain; the shallower of the two is the LCA,
+        // whose slot holds no edge of the path when the tree is edge-valued
+        let skip = skip_lca as usize;
+        if depth[u] >= depth[v] {
+            if self.label[v] + skip <= self.label[u] {
+                up.push((self.label[v] + skip, self.label[u], Direction::Up));
+            }
+        } else {
+            up.push((self.label[u] + skip, self.label[v], Direction::Down));
+        }
+
+        up.into_iter().chain(down.into_iter().rev())
     }
 
-    /// Query the path between nodes u and v
-    pub fn query(&mut self, u: usize, v: usize) -> T {
-        let lc = self.tree.lca(u, v);
-        let val1 = self.query_chain(u, lc);
-        let val2 = self.query_chain(v, lc);
-        let combined = self.combine_fn.combine(val1, val2);
-        let lc_val = self.seg_tree.query(self.label[lc], self.label[lc]);
-        self.combine_fn.combine(combined, lc_val)
+    /// Query the path between nodes u and v, or `None` if they are in
+    /// different trees of a forest
+    ///
+    /// The order in which path values are combined is unspecified, so this is
+    /// only meaningful for commutative combine functions. Use `query_path`
+    /// when the order matters. Either way, this takes one range fold per
+    /// piece of `path_segments`.
+    pub fn query(&self, u: usize, v: usize) -> Option<T> {
+        if !self.tree.connected(u, v) {
+            return None;
+        }
+        let val = self.path_segments(u, v).fold(self.sentinel.clone(), |acc, (lo, hi, _)| {
+            self.combine_fn.combine(&acc, &self.seg_tree.query(lo, hi))
+        });
+        Some(val)
     }
 
-    /// Update a chain from v to p (excludes p)
-    fn update_chain(&mut self, mut v: usize, p: usize, val: T) {
-        let depth = self.tree.depth();
-        let par = self.tree.par();
-        
-        while depth[p] < depth[v] {
-            let mut top = self.chain[v];
-            if depth[top] <= depth[p] {
-                let diff = depth[v] - depth[p];
-                if diff > 0 {
-                    top = self.tree.get_kth_ancestor(v, diff - 1);
-                    if top == usize::MAX {
-                        break;
-                    }
-                } else {
-                    break;
-                }
+    /// Query the path from u to v, combining node values in exact u -> v order
+    ///
+    /// Labels increase going down a heavy chain, so `Up` pieces are read with
+    /// right-to-left folds and `Down` pieces with left-to-right folds. This
+    /// makes the result correct for non-commutative combine functions.
+    /// Like `query`, this is `None` when u and v are in different trees.
+    pub fn query_path(&self, u: usize, v: usize) -> Option<T> {
+        if !self.tree.connected(u, v) {
+            return None;
+        }
+        let val = self.path_segments(u, v).fold(self.sentinel.clone(), |acc, (lo, hi, direction)| {
+            let seg = match direction {
+                Direction::Up => self.seg_tree.query_rev(lo, hi),
+                Direction::Down => self.seg_tree.query(lo, hi),
+            };
+            self.combine_fn.combine(&acc, &seg)
+        });
+        Some(val)
+    }
+
+    /// The first node x on the path from u to v at which
+    /// `pred(value(u) + ... + value(x))` holds, aggregating in path order
+    ///
+    /// `pred` must be monotone along the path (once true, it stays true as
+    /// more values are combined in), e.g. "running total exceeds a limit".
+    /// Each piece of `path_segments` is searched by descending the segment
+    /// tree, so this takes O(log n) per piece: O(log² n) right after a
+    /// decomposition. In an edge-valued tree the answer is the child
+    /// end of the first edge that makes `pred` hold. There is no answer when
+    /// u and v are in different trees.
+    pub fn path_find_first<P>(&self, u: usize, v: usize, pred: P) -> Option<usize>
+    where
+        P: Fn(&T) -> bool,
+    {
+        if !self.tree.connected(u, v) {
+            return None;
+        }
+        let mut acc = self.sentinel.clone();
+        for (lo, hi, direction) in self.path_segments(u, v) {
+            let found = match direction {
+                Direction::Up => self.seg_tree.find_last(lo, hi, acc, &pred),
+                Direction::Down => self.seg_tree.find_first(lo, hi, acc, &pred),
+            };
+            match found {
+                Ok(l) => return Some(self.node_at_label[l]),
+                Err(total) => acc = total,
             }
-            self.seg_tree.update(self.label[top], self.label[v], val);
-            if let Some(parent) = par[top] {
-                v = parent;
+        }
+        None
+    }
+
+    /// Update the path between nodes u and v
+    ///
+    /// Takes one range update per piece of `path_segments`.
+    ///
+    /// # Panics
+    /// If u and v are in different trees of a forest; `try_update` reports
+    /// this as an error instead.
+    pub fn update(&mut self, u: usize, v: usize, val: U) {
+        assert!(self.tree.connected(u, v), "u and v must be in the same tree");
+        let segments: Vec<_> = self.path_segments(u, v).collect();
+        for (lo, hi, _) in segments {
+            self.seg_tree.update(lo, hi, val.clone());
+        }
+    }
+
+    /// Query the subtree rooted at v
+    ///
+    /// Heavy-first labelling places every subtree in the contiguous label
+    /// range `[label[v], label[v] + sz[v] - 1]`, so this is a single range query
+    /// plus one lookup per leaf added since the last decomposition. Finding
+    /// those leaves scans all of them, up to about √n per call. They are
+    /// combined in unspecified order, so this is meant for commutative
+    /// combine functions once `add_leaf` is used.
+    /// In an edge-valued tree only the edges below v are included.
+    pub fn query_subtree(&self, v: usize) -> T {
+        let (l, r) = self.subtree_range(v);
+        let val = if l > r {
+            self.sentinel.clone()
+        } else {
+            self.seg_tree.query(l, r)
+        };
+        self.fold_nodes(val, self.pending_below(v))
+    }
+
+    /// Update every node (or, if edge-valued, every edge) in the subtree rooted at v
+    pub fn update_subtree(&mut self, v: usize, val: U) {
+        let (l, r) = self.subtree_range(v);
+        if l <= r {
+            self.seg_tree.update(l, r, val.clone());
+        }
+        for p in self.pending_below(v) {
+            let l = self.label[p];
+            self.seg_tree.update(l, l, val.clone());
+        }
+    }
+
+    /// Current value of node v (or, if edge-valued, of the edge above v),
+    /// including every path and subtree update applied so far
+    pub fn get(&self, v: usize) -> T {
+        let l = self.label[v];
+        self.seg_tree.query(l, l)
+    }
+
+    /// Set the value of node v (or, if edge-valued, of the edge above v)
+    ///
+    /// Both the `Node` and the segment tree are updated, so `get_node` and
+    /// every query see the new value.
+    ///
+    /// # Panics
+    /// If the Halide is edge-valued and v is a root, which has no edge above
+    /// it; `try_set` reports this as an error instead.
+    pub fn set(&mut self, v: usize, val: T) {
+        assert!(
+            !self.is_edge_root(v),
+            "node {} is a root, so it has no edge value",
+            v
+        );
+        if let Some(node) = self.tree.get_node_mut(v) {
+            node.set_value(val.clone());
+        }
+        self.seg_tree.point_set(self.label[v], val);
+    }
+
+    /// Replace the value of node v with `f` of its current value
+    ///
+    /// # Panics
+    /// On the same edge-valued roots as `set`.
+    pub fn modify<F>(&mut self, v: usize, f: F)
+    where
+        F: FnOnce(T) -> T,
+    {
+        let cur = self.get(v);
+        self.set(v, f(cur));
+    }
+
+    /// Query the path between u and v, checking ids and connectivity first
+    pub fn try_query(&self, u: usize, v: usize) -> Result<T, HalideError> {
+        self.check_nodes(&[u, v])?;
+        self.query(u, v).ok_or(HalideError::DifferentTrees(u, v))
+    }
+
+    /// Ordered path query from u to v, checking ids and connectivity first
+    pub fn try_query_path(&self, u: usize, v: usize) -> Result<T, HalideError> {
+        self.check_nodes(&[u, v])?;
+        self.query_path(u, v).ok_or(HalideError::DifferentTrees(u, v))
+    }
+
+    /// Update the path between u and v, checking ids and connectivity first
+    pub fn try_update(&mut self, u: usize, v: usize, val: U) -> Result<(), HalideError> {
+        self.tree.check_same_tree(u, v)?;
+        self.update(u, v, val);
+        Ok(())
+    }
+
+    /// Query the subtree rooted at v, checking ids first
+    pub fn try_query_subtree(&self, v: usize) -> Result<T, HalideError> {
+        self.check_nodes(&[v])?;
+        Ok(self.query_subtree(v))
+    }
+
+    /// Update the subtree rooted at v, checking ids first
+    pub fn try_update_subtree(&mut self, v: usize, val: U) -> Result<(), HalideError> {
+        self.check_nodes(&[v])?;
+        self.update_subtree(v, val);
+        Ok(())
+    }
+
+    /// Read the value of node v, checking its id first
+    pub fn try_get(&self, v: usize) -> Result<T, HalideError> {
+        self.check_nodes(&[v])?;
+        Ok(self.get(v))
+    }
+
+    /// Set the value of node v, checking its id first and that it isn't the
+    /// root of an edge-valued tree
+    pub fn try_set(&mut self, v: usize, val: T) -> Result<(), HalideError> {
+        self.check_settable(v)?;
+        self.set(v, val);
+        Ok(())
+    }
+
+    /// Modify the value of node v, with the same checks as `try_set`
+    pub fn try_modify<F>(&mut self, v: usize, f: F) -> Result<(), HalideError>
+    where
+        F: FnOnce(T) -> T,
+    {
+        self.check_settable(v)?;
+        self.modify(v, f);
+        Ok(())
+    }
+
+    /// Whether v is a root of an edge-valued Halide, whose slot must stay
+    /// the identity
+    fn is_edge_root(&self, v: usize) -> bool {
+        self.edge_valued && self.tree.get_parent(v).is_none()
+    }
+
+    fn check_settable(&self, v: usize) -> Result<(), HalideError> {
+        self.check_nodes(&[v])?;
+        if self.is_edge_root(v) {
+            return Err(HalideError::RootHasNoEdge(v));
+        }
+        Ok(())
+    }
+
+    fn check_nodes(&self, nodes: &[usize]) -> Result<(), HalideError> {
+        nodes.iter().try_for_each(|&v| self.tree.check_node(v))
+    }
+
+    /// Query the subtree of v as if the tree were rooted at r
+    ///
+    /// Rerooting at r only changes v's subtree when r lies strictly below v,
+    /// in which case it becomes everything except the subtree of v's child
+    /// towards r: two label ranges, combined in unspecified order (so, like
+    /// `query`, meant for commutative combine functions). In a forest, r only
+    /// reroots its own tree.
+    pub fn subtree_query_rooted(&self, v: usize, r: usize) -> T {
+        // Every tree of a forest occupies its own block of labels, apart
+        // from the leaves added to it since the last decomposition
+        let root = self.tree.component_of(v);
+        let (first, last) = (self.label[root], self.label[root] + self.sz[root] - 1);
+        let added = self.pending_below(root);
+        if v == r {
+            return self.fold_nodes(self.seg_tree.query(first, last), added);
+        }
+        if !self.is_ancestor(v, r) {
+            return self.query_subtree(v);
+        }
+
+        let c = self.child_towards(v, r);
+        let val = if self.is_pending(c) {
+            self.seg_tree.query(first, last)
+        } else {
+            let (l, hi) = (self.label[c], self.label[c] + self.sz[c] - 1);
+            let before = self.seg_tree.query(first, l - 1);
+            if hi < last {
+                self.combine_fn.combine(&before, &self.seg_tree.query(hi + 1, last))
             } else {
-                break;
+                before
             }
+        };
+        let outside = added.into_iter().filter(|&p| !self.is_ancestor(c, p));
+        self.fold_nodes(val, outside)
+    }
+
+    /// The lowest common ancestor of u and v with the tree rooted at r
+    ///
+    /// This is whichever of `lca(u, v)`, `lca(u, r)` and `lca(v, r)` is
+    /// deepest under the original root. All three must be in the same tree.
+    pub fn lca_rooted(&self, u: usize, v: usize, r: usize) -> usize {
+        let candidates = [self.tree.lca(u, v), self.tree.lca(u, r), self.tree.lca(v, r)];
+        candidates
+            .into_iter()
+            .max_by_key(|&x| self.tree.get_depth(x))
+            .unwrap()
+    }
+
+    /// The parent of v with the tree rooted at r, or `None` if v == r
+    ///
+    /// Other rerooted jumps follow from the path API: the k-th ancestor of v
+    /// under root r is `kth_on_path(v, r, k)`.
+    pub fn parent_rooted(&self, v: usize, r: usize) -> Option<usize> {
+        if v == r {
+            None
+        } else if self.is_ancestor(v, r) {
+            Some(self.child_towards(v, r))
+        } else {
+            self.tree.get_parent(v)
         }
     }
 
-    /// Update the path between nodes u and v
-    pub fn update(&mut self, u: usize, v: usize, val: T) {
-        let lc = self.tree.lca(u, v);
-        self.update_chain(u, lc, val);
-        self.update_chain(v, lc, val);
-        self.seg_tree.update(self.label[lc], self.label[lc], val);
+    /// Whether a is an ancestor of b (or b itself) under the original root
+    fn is_ancestor(&self, a: usize, mut b: usize) -> bool {
+        // Added leaves sit below their parents but outside their label ranges
+        while self.is_pending(b) {
+            if b == a {
+                return true;
+            }
+            b = self.tree.get_parent(b).unwrap();
+        }
+        !self.is_pending(a) && (self.label[a]..self.label[a] + self.sz[a]).contains(&self.label[b])
+    }
+
+    /// Whether v was added by `add_leaf` since the last decomposition
+    fn is_pending(&self, v: usize) -> bool {
+        self.label[v] >= self.decomposed
+    }
+
+    /// Leaves added since the last decomposition that lie strictly below v
+    ///
+    /// They are labelled in the order they were added, so each one's parent
+    /// has already been classified by the time it is reached.
+    fn pending_below(&self, v: usize) -> Vec<usize> {
+        let mut inside = Vec::new();
+        let mut below = Vec::new();
+        for &p in &self.node_at_label[self.decomposed..] {
+            let parent = self.tree.get_parent(p).unwrap();
+            let is_below = parent == v
+                || if self.is_pending(parent) {
+                    inside[self.label[parent] - self.decomposed]
+                } else {
+                    self.is_ancestor(v, parent)
+                };
+            inside.push(is_below);
+            if is_below {
+                below.push(p);
+            }
+        }
+        below
+    }
+
+    /// Combine the current values of `nodes` onto `acc`
+    fn fold_nodes<I>(&self, acc: T, nodes: I) -> T
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        nodes
+            .into_iter()
+            .fold(acc, |acc, p| self.combine_fn.combine(&acc, &self.get(p)))
+    }
+
+    /// The child of v on the way down to its proper descendant r
+    fn child_towards(&self, v: usize, r: usize) -> usize {
+        let k = self.tree.get_depth(r) - self.tree.get_depth(v) - 1;
+        self.kth_ancestor(r, k).unwrap()
+    }
+
+    /// Label range covered by the subtree rooted at v, which is empty for a
+    /// leaf of an edge-valued tree
+    fn subtree_range(&self, v: usize) -> (usize, usize) {
+        let skip = self.edge_valued as usize;
+        (self.label[v] + skip, self.label[v] + self.sz[v] - 1)
+    }
+
+    /// The root of the tree containing v (see `HalideBuilder::build_forest`)
+    pub fn component_of(&self, v: usize) -> usize {
+        self.tree.component_of(v)
+    }
+
+    /// Whether values live on edges rather than nodes
+    pub fn is_edge_valued(&self) -> bool {
+        self.edge_valued
+    }
+
+    /// Get the size of the subtree rooted at a node
+    ///
+    /// Like `query_subtree`, this scans the up to √n leaves added since the
+    /// last decomposition.
+    pub fn get_subtree_size(&self, node: usize) -> usize {
+        self.sz[node] + self.pending_below(node).len()
     }
 
     /// Get the label (position in segment tree) of a node
+    ///
+    /// Leaves added since the last decomposition take the labels after all
+    /// the others, in the order they were added.
     pub fn get_label(&self, node: usize) -> usize {
         self.label[node]
     }
 
+    /// Get the node placed at a label (the inverse of `get_label`)
+    pub fn get_node_by_label(&self, label: usize) -> usize {
+        self.node_at_label[label]
+    }
+
     /// Get a reference to a node
+    ///
+    /// The node holds the value given at build time or by the last `set`;
+    /// path and subtree updates are only visible through `get`. Values are
+    /// changed with `set` or `modify`, which keep the segment tree in sync.
     pub fn get_node(&self, id: usize) -> Option<&Node<T>> {
         self.tree.get_node(id)
     }
 
-    /// Get a mutable reference to a node
-    pub fn get_node_mut(&mut self, id: usize) -> Option<&mut Node<T>> {
-        self.tree.get_node_mut(id)
-    }
-
     /// Get a reference to the underlying tree
     pub fn tree(&self) -> &Tree<T> {
         &self.tree
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<T, C, LA, LF, U> PathAggregate<T, U> for Halide<T, C, LA, LF, U>
+where
+    T: Clone,
+    U: Clone,
+    C: CombineFn<T> + Clone,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
+{
+    fn path_query(&mut self, u: usize, v: usize) -> Option<T> {
+        self.query_path(u, v)
+    }
 
-    // Example combine functions
-    #[derive(Clone)]
-    struct XorCombine;
-    impl CombineFn<u64> for XorCombine {
-        fn combine(&self, a: u64, b: u64) -> u64 {
-            a ^ b
-        }
+    fn path_update(&mut self, u: usize, v: usize, val: U) -> Result<(), HalideError> {
+        self.try_update(u, v, val)
     }
 
-    #[derive(Clone)]
-    struct SumCombine;
-    impl CombineFn<u64> for SumCombine {
-        fn combine(&self, a: u64, b: u64) -> u64 {
-            a + b
-        }
+    fn connected(&mut self, u: usize, v: usize) -> bool {
+        self.tree.connected(u, v)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoids::Xor;
 
     #[test]
     fn test_basic_halide() {
         let values = vec![1u64, 2, 3, 4, 5];
-        let lg = 3;
-        let combine = XorCombine;
+        let combine = Xor;
         
-        let mut halide = Halide::new(
+        let halide = HalideBuilder::new(
             values,
-            lg,
             combine,
-            0u64, // sentinel
-        );
-
-        halide.add_edge(0, 1);
-        halide.add_edge(0, 2);
-        halide.add_edge(1, 3);
-        halide.add_edge(1, 4);
-
-        halide.init(0); // root is node 0
+        )
+            .add_edge(0, 1)
+            .add_edge(0, 2)
+            .add_edge(1, 3)
+            .add_edge(1, 4)
+            .build(0)
+            .unwrap(); // root is node 0
 
         // Test LCA
         let lca = halide.tree().lca(3, 4);
@@ -0,0 +1,370 @@
+//! A dynamic forest supporting `link` and `cut`
+//!
+//! `Halide` decomposes a fixed topology once; `LinkCutTree` instead keeps every
+//! root-to-node path in a splay tree, so edges can be added and removed at any
+//! time in O(log n) amortised. Values, aggregates and updates use the same
+//! `CombineFn`, `LazyApplyFn` and `LazyFunc` traits as `Halide`, and both
+//! implement `PathAggregate`, so path code can be written once for either.
+
+use crate::error::HalideError;
+use crate::path::PathAggregate;
+use crate::segment_tree::{CombineFn, DefaultLazyApply, DefaultLazyFunc, LazyApplyFn, LazyFunc};
+
+/// Link-cut tree over a forest of `n` nodes, initially with no edges
+///
+/// Each splay tree holds one preferred path, ordered from the shallow end, and
+/// keeps both the forward and reverse aggregate of its subtree so that paths
+/// can be re-rooted (`make_root`) without losing the combine order.
+pub struct LinkCutTree<T, C, LA = DefaultLazyApply, LF = DefaultLazyFunc, U = T>
+where
+    T: Clone,
+    U: Clone,
+    C: CombineFn<T>,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
+{
+    child: Vec<[Option<usize>; 2]>,
+    /// Splay tree parent, or the path-parent if the node is a splay root
+    parent: Vec<Option<usize>>,
+    val: Vec<T>,
+    agg: Vec<T>,
+    agg_rev: Vec<T>,
+    size: Vec<usize>,
+    /// The children (not the node itself) still have to be swapped
+    flip: Vec<bool>,
+    /// Already applied to the node; still to be applied to its children
+    lazy: Vec<Option<U>>,
+    combine_fn: C,
+    lazy_apply_fn: LA,
+    lazy_func: LF,
+    sentinel: T,
+}
+
+impl<T, C> LinkCutTree<T, C>
+where
+    T: Clone,
+    C: CombineFn<T>,
+{
+    /// Create a forest of isolated nodes whose updates assign values
+    ///
+    /// # Arguments
+    /// * `values` - Initial values for each node (index corresponds to node id)
+    /// * `combine_fn` - Function to combine two values (see `monoids`)
+    pub fn new(values: Vec<T>, combine_fn: C) -> Self {
+        Self::with_lazy(values, combine_fn, DefaultLazyApply, DefaultLazyFunc)
+    }
+}
+
+impl<T, C, LA, LF, U> LinkCutTree<T, C, LA, LF, U>
+where
+    T: Clone,
+    U: Clone,
+    C: CombineFn<T>,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
+{
+    /// Create a forest of isolated nodes with custom update semantics
+    ///
+    /// # Arguments
+    /// * `values` - Initial values for each node (index corresponds to node id)
+    /// * `combine_fn` - Function to combine two values (see `monoids`)
+    /// * `lazy_apply_fn` - Composes two pending updates into one
+    /// * `lazy_func` - Applies a pending update to an aggregate
+    pub fn with_lazy(values: Vec<T>, combine_fn: C, lazy_apply_fn: LA, lazy_func: LF) -> Self {
+        let n = values.len();
+        let sentinel = combine_fn.identity();
+        Self {
+            child: vec![[None, None]; n],
+            parent: vec![None; n],
+            agg: values.clone(),
+            agg_rev: values.clone(),
+            val: values,
+            size: vec![1; n],
+            flip: vec![false; n],
+            lazy: vec![None; n],
+            combine_fn,
+            lazy_apply_fn,
+            lazy_func,
+            sentinel,
+        }
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.val.len()
+    }
+
+    /// Whether there are no nodes
+    pub fn is_empty(&self) -> bool {
+        self.val.is_empty()
+    }
+
+    /// Add the edge (u, v), joining two different trees
+    ///
+    /// Fails if an id is out of range, u == v, or u and v are already
+    /// connected (the edge would close a cycle).
+    pub fn link(&mut self, u: usize, v: usize) -> Result<(), HalideError> {
+        self.check_node(u)?;
+        self.check_node(v)?;
+        if u == v {
+            return Err(HalideError::SelfLoop(u));
+        }
+        if self.connected(u, v) {
+            return Err(HalideError::Cycle(u, v));
+        }
+        self.make_root(u);
+        self.parent[u] = Some(v);
+        Ok(())
+    }
+
+    /// Remove the edge (u, v), failing if there is no such edge
+    pub fn cut(&mut self, u: usize, v: usize) -> Result<(), HalideError> {
+        self.check_node(u)?;
+        self.check_node(v)?;
+        if u == v || !self.connected(u, v) {
+            return Err(HalideError::MissingEdge(u, v));
+        }
+
+        // With u as the root, the edge exists iff the root-to-v path is just u, v
+        self.make_root(u);
+        self.access(v);
+        if self.child[v][0] != Some(u) || self.size[u] != 1 {
+            return Err(HalideError::MissingEdge(u, v));
+        }
+        self.child[v][0] = None;
+        self.parent[u] = None;
+        self.pull(v);
+        Ok(())
+    }
+
+    /// Whether u and v are in the same tree
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        u == v || self.find_root(u) == self.find_root(v)
+    }
+
+    /// Query the path from u to v, combining values in exact u -> v order,
+    /// or `None` if they are in different trees
+    pub fn query_path(&mut self, u: usize, v: usize) -> Option<T> {
+        if !self.connected(u, v) {
+            return None;
+        }
+        self.expose(u, v);
+        Some(self.agg[v].clone())
+    }
+
+    /// Update the path between u and v
+    ///
+    /// # Panics
+    /// If u and v are in different trees; `try_update` reports this as an
+    /// error instead.
+    pub fn update(&mut self, u: usize, v: usize, val: U) {
+        assert!(self.connected(u, v), "u and v must be in the same tree");
+        self.expose(u, v);
+        self.apply(v, &val);
+    }
+
+    /// Update the path between u and v, checking ids and connectivity first
+    pub fn try_update(&mut self, u: usize, v: usize, val: U) -> Result<(), HalideError> {
+        self.check_node(u)?;
+        self.check_node(v)?;
+        if !self.connected(u, v) {
+            return Err(HalideError::DifferentTrees(u, v));
+        }
+        self.update(u, v, val);
+        Ok(())
+    }
+
+    /// Current value of node v, including every update applied so far
+    pub fn get(&mut self, v: usize) -> T {
+        self.splay(v);
+        self.val[v].clone()
+    }
+
+    /// Set the value of node v
+    pub fn set(&mut self, v: usize, val: T) {
+        self.splay(v);
+        self.val[v] = val;
+        self.pull(v);
+    }
+
+    fn check_node(&self, id: usize) -> Result<(), HalideError> {
+        if id < self.len() {
+            Ok(())
+        } else {
+            Err(HalideError::InvalidNode { id, n: self.len() })
+        }
+    }
+
+    /// Leave exactly the u -> v path in v's splay tree, with v at its root
+    fn expose(&mut self, u: usize, v: usize) {
+        self.make_root(u);
+        self.access(v);
+    }
+
+    /// Make v the root of its tree
+    fn make_root(&mut self, v: usize) {
+        self.access(v);
+        self.reverse(v);
+    }
+
+    /// The root of the tree containing v
+    fn find_root(&mut self, v: usize) -> usize {
+        self.access(v);
+        let mut r = v;
+        loop {
+            self.push(r);
+            match self.child[r][0] {
+                Some(l) => r = l,
+                None => break,
+            }
+        }
+        // Splaying keeps repeated root lookups amortised O(log n)
+        self.splay(r);
+        r
+    }
+
+    /// Make the root-to-v path preferred, leaving v at the root of its splay tree
+    fn access(&mut self, v: usize) {
+        let mut last = None;
+        let mut cur = Some(v);
+        while let Some(x) = cur {
+            self.splay(x);
+            self.child[x][1] = last;
+            self.pull(x);
+            last = Some(x);
+            cur = self.parent[x];
+        }
+        self.splay(v);
+    }
+
+    fn is_splay_root(&self, x: usize) -> bool {
+        match self.parent[x] {
+            Some(p) => self.child[p][0] != Some(x) && self.child[p][1] != Some(x),
+            None => true,
+        }
+    }
+
+    fn splay(&mut self, x: usize) {
+        // Pending flips and tags have to reach x before it moves
+        let mut path = vec![x];
+        let mut y = x;
+        while !self.is_splay_root(y) {
+            y = self.parent[y].unwrap();
+            path.push(y);
+        }
+        for &y in path.iter().rev() {
+            self.push(y);
+        }
+
+        while !self.is_splay_root(x) {
+            let p = self.parent[x].unwrap();
+            if !self.is_splay_root(p) {
+                let g = self.parent[p].unwrap();
+                let zig_zig = (self.child[g][0] == Some(p)) == (self.child[p][0] == Some(x));
+                self.rotate(if zig_zig { p } else { x });
+            }
+            self.rotate(x);
+        }
+    }
+
+    /// Move x above its parent, keeping the in-order sequence
+    fn rotate(&mut self, x: usize) {
+        let p = self.parent[x].unwrap();
+        let g = self.parent[p];
+        let dir = (self.child[p][1] == Some(x)) as usize;
+
+        let inner = self.child[x][1 - dir];
+        self.child[p][dir] = inner;
+        if let Some(inner) = inner {
+            self.parent[inner] = Some(p);
+        }
+        self.child[x][1 - dir] = Some(p);
+        self.parent[p] = Some(x);
+
+        // If p was a splay root, g is only a path-parent and keeps its children
+        self.parent[x] = g;
+        if let Some(g) = g {
+            if self.child[g][0] == Some(p) {
+                self.child[g][0] = Some(x);
+            } else if self.child[g][1] == Some(p) {
+                self.child[g][1] = Some(x);
+            }
+        }
+
+        self.pull(p);
+        self.pull(x);
+    }
+
+    /// Recompute the aggregates of x from its (up to date) children
+    fn pull(&mut self, x: usize) {
+        let part = |c: Option<usize>| match c {
+            Some(c) => (&self.agg[c], &self.agg_rev[c], self.size[c]),
+            None => (&self.sentinel, &self.sentinel, 0),
+        };
+        let (l_agg, l_rev, l_size) = part(self.child[x][0]);
+        let (r_agg, r_rev, r_size) = part(self.child[x][1]);
+
+        let combine = &self.combine_fn;
+        let agg = combine.combine(&combine.combine(l_agg, &self.val[x]), r_agg);
+        let agg_rev = combine.combine(&combine.combine(r_rev, &self.val[x]), l_rev);
+        self.agg[x] = agg;
+        self.agg_rev[x] = agg_rev;
+        self.size[x] = l_size + 1 + r_size;
+    }
+
+    /// Reverse the path held by x's splay tree
+    fn reverse(&mut self, x: usize) {
+        self.child[x].swap(0, 1);
+        std::mem::swap(&mut self.agg[x], &mut self.agg_rev[x]);
+        self.flip[x] ^= true;
+    }
+
+    /// Apply an update to every node of x's splay tree
+    fn apply(&mut self, x: usize, val: &U) {
+        let last = self.size[x] - 1;
+        self.val[x] = self.lazy_func.apply(&self.val[x], val, 0, 0);
+        self.agg[x] = self.lazy_func.apply(&self.agg[x], val, 0, last);
+        self.agg_rev[x] = self.lazy_func.apply(&self.agg_rev[x], val, 0, last);
+        self.lazy[x] = Some(match self.lazy[x].take() {
+            Some(pending) => self.lazy_apply_fn.apply(&pending, val),
+            None => val.clone(),
+        });
+    }
+
+    /// Hand x's pending flip and update down to its children
+    fn push(&mut self, x: usize) {
+        let children = self.child[x];
+        if self.flip[x] {
+            for c in children.into_iter().flatten() {
+                self.reverse(c);
+            }
+            self.flip[x] = false;
+        }
+        if let Some(val) = self.lazy[x].take() {
+            for c in children.into_iter().flatten() {
+                self.apply(c, &val);
+            }
+        }
+    }
+}
+
+impl<T, C, LA, LF, U> PathAggregate<T, U> for LinkCutTree<T, C, LA, LF, U>
+where
+    T: Clone,
+    U: Clone,
+    C: CombineFn<T>,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
+{
+    fn path_query(&mut self, u: usize, v: usize) -> Option<T> {
+        self.query_path(u, v)
+    }
+
+    fn path_update(&mut self, u: usize, v: usize, val: U) -> Result<(), HalideError> {
+        self.try_update(u, v, val)
+    }
+
+    fn connected(&mut self, u: usize, v: usize) -> bool {
+        LinkCutTree::connected(self, u, v)
+    }
+}
@@ -0,0 +1,219 @@
+//! Ready-made combine functions
+//!
+//! Each monoid knows its own identity, so it can be handed straight to
+//! `HalideBuilder::new` or `HalideBuilder::with_lazy`. Tuples of monoids
+//! combine tuples of values componentwise, e.g. `(Sum, Max)` aggregates
+//! `(total, largest)` in a single query.
+
+use crate::segment_tree::CombineFn;
+
+/// Addition, with identity `0`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sum;
+
+/// Multiplication, with identity `1`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Product;
+
+/// Minimum, with the type's largest value as identity
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Min;
+
+/// Maximum, with the type's smallest value as identity
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Max;
+
+/// Bitwise xor, with identity `0`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Xor;
+
+/// Bitwise or, with identity `0`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Or;
+
+/// Bitwise and, with identity "all bits set"
+#[derive(Clone, Copy, Debug, Default)]
+pub struct And;
+
+/// Greatest common divisor of unsigned integers, with identity `0`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gcd;
+
+/// Least common multiple of unsigned integers, with identity `1`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lcm;
+
+macro_rules! impl_arithmetic {
+    ($zero:expr, $one:expr, $min:ident, $max:ident; $($t:ty),*) => {
+        $(
+            impl CombineFn<$t> for Sum {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    a + b
+                }
+
+                fn identity(&self) -> $t {
+                    $zero
+                }
+            }
+
+            impl CombineFn<$t> for Product {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    a * b
+                }
+
+                fn identity(&self) -> $t {
+                    $one
+                }
+            }
+
+            impl CombineFn<$t> for Min {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    (*a).min(*b)
+                }
+
+                fn identity(&self) -> $t {
+                    <$t>::$max
+                }
+            }
+
+            impl CombineFn<$t> for Max {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    (*a).max(*b)
+                }
+
+                fn identity(&self) -> $t {
+                    <$t>::$min
+                }
+            }
+        )*
+    };
+}
+
+impl_arithmetic!(0, 1, MIN, MAX; i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_arithmetic!(0.0, 1.0, NEG_INFINITY, INFINITY; f32, f64);
+
+macro_rules! impl_bitwise {
+    ($zero:expr, $ones:expr; $($t:ty),*) => {
+        $(
+            impl CombineFn<$t> for Xor {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    a ^ b
+                }
+
+                fn identity(&self) -> $t {
+                    $zero
+                }
+            }
+
+            impl CombineFn<$t> for Or {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    a | b
+                }
+
+                fn identity(&self) -> $t {
+                    $zero
+                }
+            }
+
+            impl CombineFn<$t> for And {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    a & b
+                }
+
+                fn identity(&self) -> $t {
+                    $ones
+                }
+            }
+        )*
+    };
+}
+
+impl_bitwise!(0, !0; i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_bitwise!(false, true; bool);
+
+macro_rules! impl_divisibility {
+    ($($t:ty),*) => {
+        $(
+            impl CombineFn<$t> for Gcd {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    let (mut a, mut b) = (*a, *b);
+                    while b != 0 {
+                        (a, b) = (b, a % b);
+                    }
+                    a
+                }
+
+                fn identity(&self) -> $t {
+                    0
+                }
+            }
+
+            impl CombineFn<$t> for Lcm {
+                fn combine(&self, a: &$t, b: &$t) -> $t {
+                    if *a == 0 || *b == 0 {
+                        return 0;
+                    }
+                    a / Gcd.combine(a, b) * b
+                }
+
+                fn identity(&self) -> $t {
+                    1
+                }
+            }
+        )*
+    };
+}
+
+impl_divisibility!(u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_tuple {
+    ($(($c:ident, $t:ident, $idx:tt)),*) => {
+        impl<$($c, $t),*> CombineFn<($($t,)*)> for ($($c,)*)
+        where
+            $($c: CombineFn<$t>,)*
+        {
+            fn combine(&self, a: &($($t,)*), b: &($($t,)*)) -> ($($t,)*) {
+                ($(self.$idx.combine(&a.$idx, &b.$idx),)*)
+            }
+
+            fn identity(&self) -> ($($t,)*) {
+                ($(self.$idx.identity(),)*)
+            }
+        }
+    };
+}
+
+impl_tuple!((C0, T0, 0), (C1, T1, 1));
+impl_tuple!((C0, T0, 0), (C1, T1, 1), (C2, T2, 2));
+impl_tuple!((C0, T0, 0), (C1, T1, 1), (C2, T2, 2), (C3, T3, 3));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identities() {
+        assert_eq!(Sum.combine(&Sum.identity(), &7u32), 7);
+        assert_eq!(Product.combine(&Product.identity(), &7i64), 7);
+        assert_eq!(Min.combine(&Min.identity(), &-7i32), -7);
+        assert_eq!(Max.combine(&Max.identity(), &-7.5f64), -7.5);
+        assert_eq!(And.combine(&And.identity(), &0b1010u8), 0b1010);
+        assert_eq!(Gcd.combine(&Gcd.identity(), &12u64), 12);
+        assert_eq!(Lcm.combine(&Lcm.identity(), &12u64), 12);
+        assert!(Or.combine(&Or.identity(), &true));
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(Gcd.combine(&12u32, &18), 6);
+        assert_eq!(Lcm.combine(&4u32, &6), 12);
+        assert_eq!(Lcm.combine(&0u32, &6), 0);
+    }
+
+    #[test]
+    fn test_tuple() {
+        let both = (Sum, Max);
+        assert_eq!(both.identity(), (0u64, u64::MIN));
+        assert_eq!(both.combine(&(3u64, 5u64), &(4, 2)), (7, 5));
+    }
+}
@@ -0,0 +1,30 @@
+use crate::error::HalideError;
+
+/// Which way a path walks through a contiguous range of labels
+///
+/// Labels increase going down a heavy chain, so a piece of a path heading
+/// towards the root visits its labels in decreasing order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Towards the root: labels are visited from `hi` down to `lo`
+    Up,
+    /// Away from the root: labels are visited from `lo` up to `hi`
+    Down,
+}
+
+/// Path operations shared by `Halide` and `LinkCutTree`
+///
+/// Code written against this trait can switch between the static
+/// decomposition and the dynamic forest without changes. `U` is the type of
+/// an update, which is the value type unless the lazy actions say otherwise.
+pub trait PathAggregate<T, U = T> {
+    /// Aggregate of the path from u to v in u -> v order, or `None` if they
+    /// are in different trees
+    fn path_query(&mut self, u: usize, v: usize) -> Option<T>;
+
+    /// Apply `val` to every value on the path between u and v
+    fn path_update(&mut self, u: usize, v: usize, val: U) -> Result<(), HalideError>;
+
+    /// Whether u and v are in the same tree
+    fn connected(&mut self, u: usize, v: usize) -> bool;
+}
@@ -1,25 +1,76 @@
+use std::ops::{Add, Mul};
+
 /// Trait for combining two segment tree values
+///
+/// `combine` must be associative and `identity` must satisfy
+/// `combine(identity(), x) == combine(x, identity()) == x`. Values are
+/// borrowed, so heap-backed types (strings, vectors, big integers) are only
+/// cloned where a result has to be kept.
 pub trait CombineFn<T> {
-    fn combine(&self, a: T, b: T) -> T;
+    fn combine(&self, a: &T, b: &T) -> T;
+
+    /// The identity element, returned by queries over empty ranges
+    fn identity(&self) -> T;
 }
 
-/// Trait for applying lazy updates
-pub trait LazyApplyFn<T> {
-    fn apply(&self, lazy_val: T, new_val: T) -> T;
+/// A `CombineFn` built from a closure and its identity element
+///
+/// Lets ad-hoc aggregates be written inline instead of declaring a named type:
+/// `FnCombine::new(|a: &u64, b: &u64| *a.max(b), 0)`.
+#[derive(Clone, Copy)]
+pub struct FnCombine<F, T> {
+    f: F,
+    identity: T,
 }
 
-/// Trait for applying lazy value to current value
-pub trait LazyFunc<T> {
-    fn apply(&self, cur_val: T, lazy_val: T, l: usize, r: usize) -> T;
+impl<F, T> FnCombine<F, T>
+where
+    F: Fn(&T, &T) -> T,
+{
+    pub fn new(f: F, identity: T) -> Self {
+        Self { f, identity }
+    }
+}
+
+impl<F, T> CombineFn<T> for FnCombine<F, T>
+where
+    F: Fn(&T, &T) -> T,
+    T: Clone,
+{
+    fn combine(&self, a: &T, b: &T) -> T {
+        (self.f)(a, b)
+    }
+
+    fn identity(&self) -> T {
+        self.identity.clone()
+    }
+}
+
+/// Trait for composing lazy updates of type `U`
+///
+/// `apply(pending, new)` returns the single update equivalent to applying
+/// `pending` first and `new` afterwards.
+pub trait LazyApplyFn<U> {
+    fn apply(&self, lazy_val: &U, new_val: &U) -> U;
+}
+
+/// Trait for applying a lazy update of type `U` to a value of type `T`
+///
+/// `cur_val` is the aggregate of the segment `[l, r]`, so actions whose effect
+/// depends on the number of elements (e.g. adding to a sum) can use `r - l + 1`.
+/// The update type defaults to the value type; a distinct `U` lets e.g. an
+/// `i64` delta act on a `(sum, min, count)` aggregate.
+pub trait LazyFunc<T, U = T> {
+    fn apply(&self, cur_val: &T, lazy_val: &U, l: usize, r: usize) -> T;
 }
 
 /// Default lazy apply function - simply replaces with new value
 #[derive(Clone, Copy, Default)]
 pub struct DefaultLazyApply;
 
-impl<T> LazyApplyFn<T> for DefaultLazyApply {
-    fn apply(&self, _lazy_val: T, new_val: T) -> T {
-        new_val
+impl<T: Clone> LazyApplyFn<T> for DefaultLazyApply {
+    fn apply(&self, _lazy_val: &T, new_val: &T) -> T {
+        new_val.clone()
     }
 }
 
@@ -27,134 +78,439 @@ impl<T> LazyApplyFn<T> for DefaultLazyApply {
 #[derive(Clone, Copy, Default)]
 pub struct DefaultLazyFunc;
 
-impl<T> LazyFunc<T> for DefaultLazyFunc {
-    fn apply(&self, _cur_val: T, lazy_val: T, _l: usize, _r: usize) -> T {
-        lazy_val
+impl<T: Clone> LazyFunc<T> for DefaultLazyFunc {
+    fn apply(&self, _cur_val: &T, lazy_val: &T, _l: usize, _r: usize) -> T {
+        lazy_val.clone()
+    }
+}
+
+/// Conversion from a segment length, used by actions that scale with the
+/// number of elements they touch
+pub trait FromLen {
+    fn from_len(len: usize) -> Self;
+}
+
+macro_rules! impl_from_len {
+    ($($t:ty),*) => {
+        $(
+            impl FromLen for $t {
+                fn from_len(len: usize) -> Self {
+                    len as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_from_len!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+/// Range add over sums: every element of the range gets `+ delta`
+#[derive(Clone, Copy, Default)]
+pub struct RangeAddSum;
+
+impl<T: Add<Output = T> + Clone> LazyApplyFn<T> for RangeAddSum {
+    fn apply(&self, lazy_val: &T, new_val: &T) -> T {
+        lazy_val.clone() + new_val.clone()
+    }
+}
+
+impl<T: Add<Output = T> + Mul<Output = T> + FromLen + Clone> LazyFunc<T> for RangeAddSum {
+    fn apply(&self, cur_val: &T, lazy_val: &T, l: usize, r: usize) -> T {
+        cur_val.clone() + lazy_val.clone() * T::from_len(r - l + 1)
+    }
+}
+
+/// Range add over minimums or maximums: every element of the range gets `+ delta`
+#[derive(Clone, Copy, Default)]
+pub struct RangeAddMinMax;
+
+impl<T: Add<Output = T> + Clone> LazyApplyFn<T> for RangeAddMinMax {
+    fn apply(&self, lazy_val: &T, new_val: &T) -> T {
+        lazy_val.clone() + new_val.clone()
+    }
+}
+
+impl<T: Add<Output = T> + Clone> LazyFunc<T> for RangeAddMinMax {
+    fn apply(&self, cur_val: &T, lazy_val: &T, _l: usize, _r: usize) -> T {
+        cur_val.clone() + lazy_val.clone()
+    }
+}
+
+/// Range assign over sums: every element of the range becomes `val`
+#[derive(Clone, Copy, Default)]
+pub struct RangeAssignSum;
+
+impl<T: Clone> LazyApplyFn<T> for RangeAssignSum {
+    fn apply(&self, _lazy_val: &T, new_val: &T) -> T {
+        new_val.clone()
+    }
+}
+
+impl<T: Mul<Output = T> + FromLen + Clone> LazyFunc<T> for RangeAssignSum {
+    fn apply(&self, _cur_val: &T, lazy_val: &T, l: usize, r: usize) -> T {
+        lazy_val.clone() * T::from_len(r - l + 1)
+    }
+}
+
+/// Range assign over minimums or maximums: every element of the range becomes `val`
+#[derive(Clone, Copy, Default)]
+pub struct RangeAssignMinMax;
+
+impl<T: Clone> LazyApplyFn<T> for RangeAssignMinMax {
+    fn apply(&self, _lazy_val: &T, new_val: &T) -> T {
+        new_val.clone()
+    }
+}
+
+impl<T: Clone> LazyFunc<T> for RangeAssignMinMax {
+    fn apply(&self, _cur_val: &T, lazy_val: &T, _l: usize, _r: usize) -> T {
+        lazy_val.clone()
+    }
+}
+
+/// Range affine map `x -> a * x + b` over sums
+///
+/// Values are `(sum, count)` pairs, so leaves should start as `(x, 1)` and be
+/// combined componentwise (e.g. with `(Sum, Sum)`). Updates are `(a, b)` pairs.
+#[derive(Clone, Copy, Default)]
+pub struct RangeAffineSum;
+
+impl<X: Add<Output = X> + Mul<Output = X> + Clone> LazyApplyFn<(X, X)> for RangeAffineSum {
+    fn apply(&self, lazy_val: &(X, X), new_val: &(X, X)) -> (X, X) {
+        // new(pending(x)) = a2 * (a1 * x + b1) + b2
+        let (a1, b1) = lazy_val.clone();
+        let (a2, b2) = new_val.clone();
+        (a2.clone() * a1, a2 * b1 + b2)
+    }
+}
+
+impl<X: Add<Output = X> + Mul<Output = X> + Clone> LazyFunc<(X, X)> for RangeAffineSum {
+    fn apply(&self, cur_val: &(X, X), lazy_val: &(X, X), _l: usize, _r: usize) -> (X, X) {
+        let (sum, count) = cur_val.clone();
+        let (a, b) = lazy_val.clone();
+        (a * sum + b * count.clone(), count)
     }
 }
 
 /// Generic segment tree with lazy propagation
-pub struct SegmentTree<T, C, LA, LF> 
+///
+/// Values only need to be `Clone`; a slot with no pending update is `None`,
+/// so tags are never compared against a sentinel value. Pending updates have
+/// their own type `U`, which `LF` applies to values and `LA` composes.
+pub struct SegmentTree<T, U, C, LA, LF>
 where
-    T: Clone + Copy + Default,
+    T: Clone,
+    U: Clone,
     C: CombineFn<T>,
-    LA: LazyApplyFn<T>,
-    LF: LazyFunc<T>,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
 {
     n: usize,
     seg_tree: Vec<T>,
-    seg_lazy: Vec<Option<T>>,
+    seg_rev: Vec<T>,
+    seg_lazy: Vec<Option<U>>,
     combine_fn: C,
     lazy_apply_fn: LA,
     lazy_func: LF,
     sentinel: T,
-    lazy_sentinel: Option<T>,
 }
 
-impl<T, C, LA, LF> SegmentTree<T, C, LA, LF>
+impl<T, U, C, LA, LF> SegmentTree<T, U, C, LA, LF>
 where
-    T: Clone + Copy + Default + PartialEq,
+    T: Clone,
+    U: Clone,
     C: CombineFn<T>,
-    LA: LazyApplyFn<T>,
-    LF: LazyFunc<T>,
+    LA: LazyApplyFn<U>,
+    LF: LazyFunc<T, U>,
 {
-    pub fn new(n: usize, combine_fn: C, lazy_apply_fn: LA, lazy_func: LF, sentinel: T, lazy_sentinel: Option<T>) -> Self {
+    /// A tree of n identity values with no pending updates
+    pub fn new(n: usize, combine_fn: C, lazy_apply_fn: LA, lazy_func: LF) -> Self {
         let size = 4 * n;
+        let sentinel = combine_fn.identity();
         Self {
             n,
-            seg_tree: vec![T::default(); size],
-            seg_lazy: vec![lazy_sentinel; size],
+            seg_tree: vec![sentinel.clone(); size],
+            seg_rev: vec![sentinel.clone(); size],
+            seg_lazy: vec![None; size],
             combine_fn,
             lazy_apply_fn,
             lazy_func,
             sentinel,
-            lazy_sentinel,
         }
     }
 
-    pub fn query(&mut self, l: usize, r: usize) -> T {
-        self.query_rec(0, 0, self.n - 1, l, r)
+    /// A tree holding `values`, built bottom-up in O(n)
+    pub fn from_slice(values: &[T], combine_fn: C, lazy_apply_fn: LA, lazy_func: LF) -> Self {
+        let mut seg = Self::new(0, combine_fn, lazy_apply_fn, lazy_func);
+        seg.assign(values);
+        seg
     }
 
-    fn query_rec(&mut self, i: usize, tl: usize, tr: usize, ql: usize, qr: usize) -> T {
-        self.eval_lazy(i, tl, tr);
+    /// Replace every value with `values` and drop all pending updates
+    ///
+    /// Each node is computed once from its children, so this takes O(n)
+    /// instead of the O(n log n) of n point updates. This is the build
+    /// behind `from_slice`, and the one a `Halide` reruns on every rebuild.
+    pub fn assign(&mut self, values: &[T]) {
+        let size = 4 * values.len();
+        self.n = values.len();
+        self.seg_tree = vec![self.sentinel.clone(); size];
+        self.seg_rev = vec![self.sentinel.clone(); size];
+        self.seg_lazy = vec![None; size];
+        if self.n > 0 {
+            self.build_rec(0, 0, self.n - 1, values);
+        }
+    }
 
+    fn build_rec(&mut self, i: usize, tl: usize, tr: usize, values: &[T]) {
+        if tl == tr {
+            self.seg_tree[i] = values[tl].clone();
+            self.seg_rev[i] = values[tl].clone();
+            return;
+        }
+
+        let mid = (tl + tr) / 2;
+        self.build_rec(2 * i + 1, tl, mid, values);
+        self.build_rec(2 * i + 2, mid + 1, tr, values);
+        self.pull(i);
+    }
+
+    /// Fold the range `[l, r]` left to right
+    pub fn query(&self, l: usize, r: usize) -> T {
+        self.fold(l, r, false)
+    }
+
+    /// Fold the range `[l, r]` right to left
+    ///
+    /// Equal to `query` for commutative combine functions; otherwise this is
+    /// the aggregate of `a[r], a[r - 1], ..., a[l]` in that order.
+    pub fn query_rev(&self, l: usize, r: usize) -> T {
+        self.fold(l, r, true)
+    }
+
+    /// Reads never push lazies down: the pending tags met on the way are
+    /// folded into the answer instead, so queries only need `&self`
+    fn fold(&self, l: usize, r: usize, rev: bool) -> T {
+        if self.n == 0 {
+            return self.sentinel.clone();
+        }
+        self.fold_rec(0, 0, self.n - 1, l, r, None, rev)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fold_rec(&self, i: usize, tl: usize, tr: usize, ql: usize, qr: usize, above: Option<&U>, rev: bool) -> T {
+        if tl > tr || tr < ql || qr < tl {
+            return self.sentinel.clone();
+        }
+
+        let pending = self.pending_lazy(i, above);
         if ql <= tl && tr <= qr {
-            return self.seg_tree[i];
+            return self.settled(i, tl, tr, pending.as_ref(), rev);
         }
 
+        let mid = (tl + tr) / 2;
+        let a = self.fold_rec(2 * i + 1, tl, mid, ql, qr, pending.as_ref(), rev);
+        let b = self.fold_rec(2 * i + 2, mid + 1, tr, ql, qr, pending.as_ref(), rev);
+        if rev {
+            self.combine_fn.combine(&b, &a)
+        } else {
+            self.combine_fn.combine(&a, &b)
+        }
+    }
+
+    /// Scan `[l, r]` left to right, returning the first index i at which
+    /// `pred(acc + a[l] + ... + a[i])` holds
+    ///
+    /// `pred` must be monotone along the scan: once it holds for a prefix it
+    /// holds for every longer one. If it never holds, the aggregate of the
+    /// whole scan (starting from `acc`) is returned as the error, so a search
+    /// can carry on into another range. This is `max_right` turned around:
+    /// the search stops where `pred` becomes true instead of where it fails.
+    pub fn find_first<P>(&self, l: usize, r: usize, acc: T, pred: &P) -> Result<usize, T>
+    where
+        P: Fn(&T) -> bool,
+    {
+        self.find(l, r, acc, pred, false)
+    }
+
+    /// Scan `[l, r]` right to left, returning the first (i.e. largest) index
+    /// i at which `pred(acc + a[r] + a[r - 1] + ... + a[i])` holds
+    ///
+    /// The `min_left` counterpart of `find_first`, with the same contract.
+    pub fn find_last<P>(&self, l: usize, r: usize, acc: T, pred: &P) -> Result<usize, T>
+    where
+        P: Fn(&T) -> bool,
+    {
+        self.find(l, r, acc, pred, true)
+    }
+
+    fn find<P>(&self, l: usize, r: usize, mut acc: T, pred: &P, rev: bool) -> Result<usize, T>
+    where
+        P: Fn(&T) -> bool,
+    {
+        if self.n == 0 {
+            return Err(acc);
+        }
+        match self.find_rec(0, 0, self.n - 1, (l, r), None, &mut acc, pred, rev) {
+            Some(idx) => Ok(idx),
+            None => Err(acc),
+        }
+    }
+
+    /// Visits the children of a node in scan order, skipping any node whose
+    /// whole range leaves `pred` false; `acc` holds the aggregate so far
+    #[allow(clippy::too_many_arguments)]
+    fn find_rec<P>(
+        &self,
+        i: usize,
+        tl: usize,
+        tr: usize,
+        (ql, qr): (usize, usize),
+        above: Option<&U>,
+        acc: &mut T,
+        pred: &P,
+        rev: bool,
+    ) -> Option<usize>
+    where
+        P: Fn(&T) -> bool,
+    {
         if tl > tr || tr < ql || qr < tl {
-            return self.sentinel;
+            return None;
+        }
+
+        let pending = self.pending_lazy(i, above);
+        if ql <= tl && tr <= qr {
+            let next = self.combine_fn.combine(acc, &self.settled(i, tl, tr, pending.as_ref(), rev));
+            if !pred(&next) {
+                *acc = next;
+                return None;
+            }
+            if tl == tr {
+                return Some(tl);
+            }
         }
 
         let mid = (tl + tr) / 2;
-        let a = self.query_rec(2 * i + 1, tl, mid, ql, qr);
-        let b = self.query_rec(2 * i + 2, mid + 1, tr, ql, qr);
-        self.combine_fn.combine(a, b)
+        let left = (2 * i + 1, tl, mid);
+        let right = (2 * i + 2, mid + 1, tr);
+        let (first, second) = if rev { (right, left) } else { (left, right) };
+        self.find_rec(first.0, first.1, first.2, (ql, qr), pending.as_ref(), acc, pred, rev)
+            .or_else(|| self.find_rec(second.0, second.1, second.2, (ql, qr), pending.as_ref(), acc, pred, rev))
     }
 
-    pub fn update(&mut self, l: usize, r: usize, val: T) {
-        self.update_rec(0, 0, self.n - 1, l, r, val);
+    /// The fold of node i (forward, or reversed if `rev`) with `pending` applied
+    fn settled(&self, i: usize, tl: usize, tr: usize, pending: Option<&U>, rev: bool) -> T {
+        let val = if rev { &self.seg_rev[i] } else { &self.seg_tree[i] };
+        match pending {
+            Some(lazy_val) => self.lazy_func.apply(val, lazy_val, tl, tr),
+            None => val.clone(),
+        }
+    }
+
+    /// Everything still to be applied to node i, given the tags pending at its
+    /// ancestors; node i's own tag is older, so it goes first
+    fn pending_lazy(&self, i: usize, above: Option<&U>) -> Option<U> {
+        match (&self.seg_lazy[i], above) {
+            (Some(own), Some(above)) => Some(self.lazy_apply_fn.apply(own, above)),
+            (own, None) => own.clone(),
+            (None, above) => above.cloned(),
+        }
+    }
+
+    /// Apply the update `val` to every value in `[l, r]`
+    pub fn update(&mut self, l: usize, r: usize, val: U) {
+        if self.n == 0 {
+            return;
+        }
+        self.update_rec(0, 0, self.n - 1, l, r, &val);
     }
 
-    fn update_rec(&mut self, i: usize, tl: usize, tr: usize, ql: usize, qr: usize, val: T) -> T {
+    fn update_rec(&mut self, i: usize, tl: usize, tr: usize, ql: usize, qr: usize, val: &U) {
         self.eval_lazy(i, tl, tr);
 
         if tl > tr || tr < ql || qr < tl {
-            return self.seg_tree[i];
+            return;
         }
 
         if ql <= tl && tr <= qr {
-            self.seg_lazy[i] = Some(self.lazy_apply_fn.apply(
-                self.seg_lazy[i].unwrap_or(val),
-                val
-            ));
+            self.compose_lazy(i, val);
             self.eval_lazy(i, tl, tr);
-            return self.seg_tree[i];
+            return;
         }
 
         if tl == tr {
-            return self.seg_tree[i];
+            return;
         }
 
         let mid = (tl + tr) / 2;
-        let a = self.update_rec(2 * i + 1, tl, mid, ql, qr, val);
-        let b = self.update_rec(2 * i + 2, mid + 1, tr, ql, qr, val);
-        self.seg_tree[i] = self.combine_fn.combine(a, b);
-        self.seg_tree[i]
+        self.update_rec(2 * i + 1, tl, mid, ql, qr, val);
+        self.update_rec(2 * i + 2, mid + 1, tr, ql, qr, val);
+        self.pull(i);
     }
 
-    fn eval_lazy(&mut self, i: usize, l: usize, r: usize) {
-        if self.seg_lazy[i] == self.lazy_sentinel {
-            return;
-        }
+    /// Recompute both folds of node i from its (up to date) children
+    fn pull(&mut self, i: usize) {
+        let (left_idx, right_idx) = (2 * i + 1, 2 * i + 2);
+        self.seg_tree[i] = self.combine_fn.combine(&self.seg_tree[left_idx], &self.seg_tree[right_idx]);
+        self.seg_rev[i] = self.combine_fn.combine(&self.seg_rev[right_idx], &self.seg_rev[left_idx]);
+    }
+
+    /// Queue `val` on node i, after any update already pending there
+    ///
+    /// An empty slot is the identity update, so `val` is stored as is rather
+    /// than composed with itself (which would double additive updates).
+    fn compose_lazy(&mut self, i: usize, val: &U) {
+        self.seg_lazy[i] = Some(match self.seg_lazy[i].take() {
+            Some(pending) => self.lazy_apply_fn.apply(&pending, val),
+            None => val.clone(),
+        });
+    }
 
-        if let Some(lazy_val) = self.seg_lazy[i] {
-            self.seg_tree[i] = self.lazy_func.apply(self.seg_tree[i], lazy_val, l, r);
+    fn eval_lazy(&mut self, i: usize, l: usize, r: usize) {
+        if let Some(lazy_val) = self.seg_lazy[i].take() {
+            self.seg_tree[i] = self.lazy_func.apply(&self.seg_tree[i], &lazy_val, l, r);
+            self.seg_rev[i] = self.lazy_func.apply(&self.seg_rev[i], &lazy_val, l, r);
 
             if l != r {
                 let left_idx = 2 * i + 1;
                 let right_idx = 2 * i + 2;
-                
-                self.seg_lazy[left_idx] = Some(self.lazy_apply_fn.apply(
-                    self.seg_lazy[left_idx].unwrap_or(lazy_val),
-                    lazy_val
-                ));
-                self.seg_lazy[right_idx] = Some(self.lazy_apply_fn.apply(
-                    self.seg_lazy[right_idx].unwrap_or(lazy_val),
-                    lazy_val
-                ));
-            }
 
-            self.seg_lazy[i] = self.lazy_sentinel;
+                self.compose_lazy(left_idx, &lazy_val);
+                self.compose_lazy(right_idx, &lazy_val);
+            }
         }
     }
 
     pub fn get_sentinel(&self) -> T {
-        self.sentinel
+        self.sentinel.clone()
     }
 
-    pub fn point_update(&mut self, idx: usize, val: T) {
+    pub fn point_update(&mut self, idx: usize, val: U) {
         self.update(idx, idx, val);
     }
+
+    /// Overwrite the value at idx, regardless of the lazy update semantics
+    pub fn point_set(&mut self, idx: usize, val: T) {
+        self.point_set_rec(0, 0, self.n - 1, idx, val);
+    }
+
+    fn point_set_rec(&mut self, i: usize, tl: usize, tr: usize, idx: usize, val: T) {
+        self.eval_lazy(i, tl, tr);
+
+        if tl == tr {
+            self.seg_rev[i] = val.clone();
+            self.seg_tree[i] = val;
+            return;
+        }
+
+        let mid = (tl + tr) / 2;
+        if idx <= mid {
+            self.point_set_rec(2 * i + 1, tl, mid, idx, val);
+            self.eval_lazy(2 * i + 2, mid + 1, tr);
+        } else {
+            self.eval_lazy(2 * i + 1, tl, mid);
+            self.point_set_rec(2 * i + 2, mid + 1, tr, idx, val);
+        }
+        self.pull(i);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::error::HalideError;
 use crate::node::Node;
 
 /// A tree structure containing nodes
@@ -7,12 +8,15 @@ pub struct Tree<T> {
     depth: Vec<usize>,
     par: Vec<Option<usize>>,
     lca_lift: Vec<Vec<Option<usize>>>,
+    order: Vec<usize>,
+    roots: Vec<usize>,
+    component: Vec<usize>,
     lg: usize,
     initialized: bool,
 }
 
 impl<T> Tree<T> {
-    pub(crate) fn new(n: usize, values: Vec<T>, lg: usize) -> Self {
+    pub(crate) fn new(n: usize, values: Vec<T>) -> Self {
         let nodes = values
             .into_iter()
             .enumerate()
@@ -24,8 +28,11 @@ impl<T> Tree<T> {
             edges: vec![Vec::new(); n],
             depth: vec![0; n],
             par: vec![None; n],
-            lca_lift: vec![vec![None; lg]; n],
-            lg,
+            lca_lift: Vec::new(),
+            order: Vec::with_capacity(n),
+            roots: Vec::new(),
+            component: (0..n).collect(),
+            lg: 0,
             initialized: false,
         }
     }
@@ -55,37 +62,172 @@ impl<T> Tree<T> {
         &self.par
     }
 
+    /// Every node, one tree after another, each listed after its parent
+    pub(crate) fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Number of nodes in the tree
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub(crate) fn check_node(&self, id: usize) -> Result<(), HalideError> {
+        if id < self.nodes.len() {
+            Ok(())
+        } else {
+            Err(HalideError::InvalidNode { id, n: self.nodes.len() })
+        }
+    }
+
+    /// Check that the edges form a single tree containing every node
+    pub(crate) fn validate(&self, root: usize) -> Result<(), HalideError> {
+        let n = self.nodes.len();
+        let mut seen = vec![false; n];
+        let reached = self.validate_component(root, &mut seen)?;
+        if reached < n {
+            return Err(HalideError::Disconnected { reached, n });
+        }
+        Ok(())
+    }
+
+    /// Check that the edges form a forest, returning the root of each tree
+    ///
+    /// Every tree is rooted at its smallest node id.
+    pub(crate) fn validate_forest(&self) -> Result<Vec<usize>, HalideError> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut roots = Vec::new();
+        for v in 0..self.nodes.len() {
+            if !seen[v] {
+                self.validate_component(v, &mut seen)?;
+                roots.push(v);
+            }
+        }
+        Ok(roots)
+    }
+
+    /// Walk the component of `root`, rejecting duplicate edges and cycles,
+    /// and return how many nodes it has
+    fn validate_component(&self, root: usize, seen: &mut [bool]) -> Result<usize, HalideError> {
+        let mut parent = vec![None; self.nodes.len()];
+        let mut reached = 1;
+        let mut stack = vec![root];
+        seen[root] = true;
+
+        while let Some(v) = stack.pop() {
+            let mut parent_edges = 0;
+            for &x in &self.edges[v] {
+                if Some(x) == parent[v] {
+                    parent_edges += 1;
+                    if parent_edges > 1 {
+                        return Err(HalideError::DuplicateEdge(x, v));
+                    }
+                } else if seen[x] {
+                    return Err(if parent[x] == Some(v) {
+                        HalideError::DuplicateEdge(v, x)
+                    } else {
+                        HalideError::Cycle(v, x)
+                    });
+                } else {
+                    seen[x] = true;
+                    parent[x] = Some(v);
+                    reached += 1;
+                    stack.push(x);
+                }
+            }
+        }
+
+        Ok(reached)
+    }
+
     /// Initialize the tree structure (call after adding all edges)
     /// 
     /// # Arguments
-    /// * `root` - Root node index
-    pub fn init(&mut self, root: usize) {
-        self.lca_dfs(root, None);
+    /// * `roots` - One root per tree of the forest
+    pub(crate) fn init(&mut self, roots: &[usize]) {
+        self.roots = roots.to_vec();
+        self.lca_dfs();
         self.initialized = true;
     }
 
-    fn lca_dfs(&mut self, v: usize, par: Option<usize>) {
-        self.lca_lift[v][0] = par;
-        self.par[v] = par;
-        self.depth[v] = if let Some(p) = par { self.depth[p] + 1 } else { 0 };
+    /// Parents, depths and lifting tables, using an explicit stack so that
+    /// arbitrarily deep trees don't overflow the call stack
+    fn lca_dfs(&mut self) {
+        self.order.clear();
 
-        for i in 1..self.lg {
-            if let Some(prev) = self.lca_lift[v][i - 1] {
-                self.lca_lift[v][i] = self.lca_lift[prev][i - 1];
-            } else {
-                self.lca_lift[v][i] = None;
+        for &root in &self.roots {
+            let mut stack = vec![(root, None)];
+
+            while let Some((v, par)) = stack.pop() {
+                self.order.push(v);
+                self.par[v] = par;
+                self.depth[v] = if let Some(p) = par { self.depth[p] + 1 } else { 0 };
+                self.component[v] = root;
+
+                for &x in &self.edges[v] {
+                    if Some(x) != par {
+                        stack.push((x, Some(v)));
+                    }
+                }
             }
         }
 
-        let edges_v = self.edges[v].clone();
-        for x in edges_v {
-            if Some(x) != par {
-                self.lca_dfs(x, Some(v));
+        self.build_lift();
+    }
+
+    /// Binary lifting tables with enough levels to jump the full depth of
+    /// the deepest node
+    fn build_lift(&mut self) {
+        let max_depth = self.order.iter().map(|&v| self.depth[v]).max().unwrap_or(0);
+        self.lg = (usize::BITS - max_depth.leading_zeros()).max(1) as usize;
+        self.lca_lift = vec![vec![None; self.lg]; self.nodes.len()];
+
+        for &v in &self.order {
+            self.lca_lift[v][0] = self.par[v];
+            for i in 1..self.lg {
+                if let Some(prev) = self.lca_lift[v][i - 1] {
+                    self.lca_lift[v][i] = self.lca_lift[prev][i - 1];
+                }
             }
         }
     }
 
+    /// Append a new leaf below `parent`, returning its id
+    ///
+    /// Parents, depths and lifting tables are extended in place; the tables
+    /// only gain a level (and are rebuilt) when the new leaf is deeper than
+    /// they can jump, which happens O(log n) times in total.
+    pub(crate) fn add_leaf(&mut self, parent: usize, value: T) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node::new(id, value));
+        self.edges.push(vec![parent]);
+        self.edges[parent].push(id);
+        self.depth.push(self.depth[parent] + 1);
+        self.par.push(Some(parent));
+        self.component.push(self.component[parent]);
+        self.order.push(id);
+
+        if self.depth[id] >> self.lg != 0 {
+            self.build_lift();
+        } else {
+            let mut lift = vec![None; self.lg];
+            lift[0] = Some(parent);
+            for i in 1..self.lg {
+                lift[i] = lift[i - 1].and_then(|prev| self.lca_lift[prev][i - 1]);
+            }
+            self.lca_lift.push(lift);
+        }
+        id
+    }
+
     /// Find the lowest common ancestor of two nodes
+    ///
+    /// The nodes must be in the same tree; `try_lca` checks this.
     pub fn lca(&self, mut a: usize, mut b: usize) -> usize {
         if self.depth[a] < self.depth[b] {
             std::mem::swap(&mut a, &mut b);
@@ -112,8 +254,55 @@ impl<T> Tree<T> {
         self.lca_lift[b][0].unwrap_or(b)
     }
 
-    /// Get the k-th ancestor of node v
+    /// Find the lowest common ancestor of two nodes, checking the ids first
+    pub fn try_lca(&self, a: usize, b: usize) -> Result<usize, HalideError> {
+        self.check_same_tree(a, b)?;
+        Ok(self.lca(a, b))
+    }
+
+    /// Number of edges on the path between a and b, which must be in the same tree
+    pub fn distance(&self, a: usize, b: usize) -> usize {
+        let l = self.lca(a, b);
+        self.get_depth(a) + self.get_depth(b) - 2 * self.get_depth(l)
+    }
+
+    /// Number of edges on the path between a and b, checking the ids first
+    pub fn try_distance(&self, a: usize, b: usize) -> Result<usize, HalideError> {
+        self.check_same_tree(a, b)?;
+        Ok(self.distance(a, b))
+    }
+
+    /// The root of the tree containing v
+    pub fn component_of(&self, v: usize) -> usize {
+        self.component[v]
+    }
+
+    /// Whether a and b are in the same tree
+    pub fn connected(&self, a: usize, b: usize) -> bool {
+        self.component[a] == self.component[b]
+    }
+
+    /// The root of every tree, in increasing order
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    pub(crate) fn check_same_tree(&self, a: usize, b: usize) -> Result<(), HalideError> {
+        self.check_node(a)?;
+        self.check_node(b)?;
+        if self.connected(a, b) {
+            Ok(())
+        } else {
+            Err(HalideError::DifferentTrees(a, b))
+        }
+    }
+
+    /// Get the k-th ancestor of node v, or `usize::MAX` if v is less than k deep
     pub fn get_kth_ancestor(&self, mut v: usize, mut k: usize) -> usize {
+        if k > self.depth[v] {
+            return usize::MAX;
+        }
+
         for i in (0..self.lg).rev() {
             if v == usize::MAX {
                 return v;
@@ -1,58 +1,28 @@
-use halide::{Halide, CombineFn, Tree};
-
-#[derive(Clone)]
-struct XorCombine;
-impl CombineFn<u64> for XorCombine {
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a ^ b
-    }
-}
-
-#[derive(Clone)]
-struct SumCombine;
-impl CombineFn<u64> for SumCombine {
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a + b
-    }
-}
-
-#[derive(Clone)]
-struct MaxCombine;
-impl CombineFn<u64> for MaxCombine {
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a.max(b)
-    }
-}
-
-#[derive(Clone)]
-struct MinCombine;
-impl CombineFn<u64> for MinCombine {
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a.min(b)
-    }
-}
+use bipath::{HalideBuilder, FnCombine, RangeAssignMinMax, RangeAssignSum};
+use bipath::monoids::{Max, Min, Sum, Xor};
 
 #[test]
 fn test_single_node() {
     let values = vec![42u64];
-    let mut halide = Halide::new(values, 1, XorCombine, 0u64);
-    halide.init(0);
+    let builder = HalideBuilder::new(values, Xor);
+    let halide = builder.build(0).unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(0), 0);
     assert_eq!(tree.get_parent(0), None);
     assert_eq!(tree.lca(0, 0), 0);
     
-    let result = halide.query(0, 0);
+    let result = halide.query(0, 0).unwrap();
     assert_eq!(result, 42);
 }
 
 #[test]
 fn test_two_nodes() {
     let values = vec![1u64, 2];
-    let mut halide = Halide::new(values, 2, SumCombine, 0u64);
-    halide.add_edge(0, 1);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(0), 0);
@@ -62,7 +32,7 @@ fn test_two_nodes() {
     assert_eq!(tree.lca(0, 1), 0);
     assert_eq!(tree.lca(1, 0), 0);
     
-    let result = halide.query(0, 1);
+    let result = halide.query(0, 1).unwrap();
     assert_eq!(result, 3); // 1 + 2
 }
 
@@ -70,13 +40,13 @@ fn test_two_nodes() {
 fn test_linear_tree() {
     // 0 - 1 - 2 - 3 - 4
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(1, 2);
-    halide.add_edge(2, 3);
-    halide.add_edge(3, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(1, 2)
+        .add_edge(2, 3)
+        .add_edge(3, 4)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(0), 0);
@@ -90,11 +60,11 @@ fn test_linear_tree() {
     assert_eq!(tree.lca(1, 3), 1);
     
     // Query entire path
-    let result = halide.query(0, 4);
+    let result = halide.query(0, 4).unwrap();
     assert_eq!(result, 15); // 1 + 2 + 3 + 4 + 5
     
     // Query partial path
-    let result = halide.query(2, 4);
+    let result = halide.query(2, 4).unwrap();
     assert_eq!(result, 12); // 3 + 4 + 5
 }
 
@@ -104,12 +74,12 @@ fn test_star_tree() {
     //  / | \
     // 1  2  3
     let values = vec![10u64, 20, 30, 40];
-    let mut halide = Halide::new(values, 2, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(0, 3);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(0, 3)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(0), 0);
@@ -121,7 +91,7 @@ fn test_star_tree() {
     assert_eq!(tree.lca(1, 3), 0);
     assert_eq!(tree.lca(2, 3), 0);
     
-    let result = halide.query(1, 2);
+    let result = halide.query(1, 2).unwrap();
     assert_eq!(result, 60); // 20 + 10 + 30
 }
 
@@ -133,15 +103,15 @@ fn test_binary_tree() {
     //  / \   / \
     // 3   4 5   6
     let values = vec![1u64, 2, 3, 4, 5, 6, 7];
-    let mut halide = Halide::new(values, 3, XorCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.add_edge(2, 5);
-    halide.add_edge(2, 6);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Xor)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .add_edge(2, 5)
+        .add_edge(2, 6)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.lca(3, 4), 1);
@@ -149,7 +119,7 @@ fn test_binary_tree() {
     assert_eq!(tree.lca(3, 5), 0);
     assert_eq!(tree.lca(4, 6), 0);
     
-    let result = halide.query(3, 5);
+    let result = halide.query(3, 5).unwrap();
     // Path: 3 -> 1 -> 0 -> 2 -> 5
     // query_chain(3, 0) = 3 XOR 2 (excludes 0)
     // query_chain(5, 0) = 5 XOR 3 (excludes 0)  
@@ -163,13 +133,13 @@ fn test_binary_tree() {
 fn test_kth_ancestor() {
     // 0 - 1 - 2 - 3 - 4
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(1, 2);
-    halide.add_edge(2, 3);
-    halide.add_edge(3, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(1, 2)
+        .add_edge(2, 3)
+        .add_edge(3, 4)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_kth_ancestor(4, 0), 4);
@@ -179,16 +149,40 @@ fn test_kth_ancestor() {
     assert_eq!(tree.get_kth_ancestor(4, 4), 0);
 }
 
+#[test]
+fn test_path_nodes_and_distance() {
+    //     0
+    //    / \
+    //   1   2
+    //  / \
+    // 3   4
+    let values = vec![1u64, 2, 3, 4, 5];
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
+
+    assert_eq!(halide.path_nodes(3, 2).collect::<Vec<_>>(), vec![3, 1, 0, 2]);
+    assert_eq!(halide.path_nodes(2, 4).collect::<Vec<_>>(), vec![2, 0, 1, 4]);
+    assert_eq!(halide.path_nodes(3, 3).collect::<Vec<_>>(), vec![3]);
+    assert_eq!(halide.tree().distance(3, 2), 3);
+    assert_eq!(halide.tree().distance(3, 4), 2);
+    assert_eq!(halide.tree().distance(1, 1), 0);
+}
+
 #[test]
 fn test_path_query_xor() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, XorCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Xor)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
     
     // Query path 3 -> 4 (LCA is 1)
     // query_chain(3, 1) = 3 (excludes 1)
@@ -198,7 +192,7 @@ fn test_path_query_xor() {
     // But actual result is 3, which means query_chain might work differently
     // Let's verify the actual behavior: result should be 3 XOR 4 XOR 2 = 5, but it's 3
     // This suggests query_chain(4, 1) might be returning sentinel (0) or something else
-    let result = halide.query(3, 4);
+    let result = halide.query(3, 4).unwrap();
     // Path includes: 3, 1, 4. Values: 3, 2, 4. XOR = 3 XOR 2 XOR 4 = 5
     // But if query_chain excludes properly, it might be: 3 XOR 0 XOR 2 = 1? Or 3 XOR 4 XOR 2 = 5?
     // Let's just check it's a reasonable value
@@ -208,54 +202,53 @@ fn test_path_query_xor() {
 #[test]
 fn test_path_query_max() {
     let values = vec![10u64, 5, 20, 15, 8];
-    let mut halide = Halide::new(values, 3, MaxCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
-    
-    let result = halide.query(3, 4);
+    let halide = HalideBuilder::new(values, Max)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
+    
+    let result = halide.query(3, 4).unwrap();
     // Path: 3 -> 1 -> 4, values: 15, 5, 8
     // max(15, 5, 8) = 15, but actual might be different based on query_chain behavior
-    assert!(result >= 8 && result <= 15);
+    assert!((8..=15).contains(&result));
 }
 
 #[test]
 fn test_path_query_min() {
     let values = vec![10u64, 5, 20, 15, 8];
-    let mut halide = Halide::new(values, 3, MinCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
-    
-    let result = halide.query(3, 4);
+    let halide = HalideBuilder::new(values, Min)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
+    
+    let result = halide.query(3, 4).unwrap();
     // Path: 3 -> 1 -> 4, values: 15, 5, 8
-    // min(15, 5, 8) = 5, but query_chain might work differently
-    // The result should be at least the minimum value in the tree
-    assert!(result >= 0); // Just verify it's a valid result
+    // min(15, 5, 8) = 5
+    assert_eq!(result, 5);
 }
 
 #[test]
 fn test_path_update() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let mut halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
     
     // Update path from 3 to 4 with value 10
     halide.update(3, 4, 10);
     
     // Query should return updated values
-    let result = halide.query(3, 4);
+    let result = halide.query(3, 4).unwrap();
     // After update, all nodes on path should have value 10
     // Path: 3 -> 1 -> 4, so result = 10 + 10 + 10 = 30
     assert_eq!(result, 30);
@@ -264,46 +257,45 @@ fn test_path_update() {
 #[test]
 fn test_multiple_queries() {
     let values = vec![1u64, 2, 3, 4, 5, 6, 7];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.add_edge(2, 5);
-    halide.add_edge(2, 6);
-    halide.init(0);
-    
-    let q1 = halide.query(3, 4);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .add_edge(2, 5)
+        .add_edge(2, 6)
+        .build(0)
+        .unwrap();
+    
+    let q1 = halide.query(3, 4).unwrap();
     // Path: 3 -> 1 -> 4, values: 3, 2, 4
     // query_chain(3, 1) = 3, query_chain(4, 1) = 4, LCA = 2
     // Result = 3 + 4 + 2 = 9
-    assert!(q1 >= 2 && q1 <= 15);
+    assert!((2..=15).contains(&q1));
     
-    let q2 = halide.query(5, 6);
+    let q2 = halide.query(5, 6).unwrap();
     // Path: 5 -> 2 -> 6, values: 5, 3, 6
     // query_chain(5, 2) = 5, query_chain(6, 2) = 6, LCA = 3
     // Result = 5 + 6 + 3 = 14
-    assert!(q2 >= 3 && q2 <= 20);
+    assert!((3..=20).contains(&q2));
     
-    let q3 = halide.query(3, 5);
+    let q3 = halide.query(3, 5).unwrap();
     // Path: 3 -> 1 -> 0 -> 2 -> 5
     // Values on path: 3, 2, 1, 3, 5
-    assert!(q3 >= 1 && q3 <= 20);
+    assert!((1..=20).contains(&q3));
 }
 
 #[test]
 fn test_different_root() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    
-    // Initialize with node 1 as root
-    halide.init(1);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        // Initialize with node 1 as root
+        .build(1)
+        .unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(1), 0);
@@ -313,3 +305,90 @@ fn test_different_root() {
     assert_eq!(tree.get_depth(2), 2);
 }
 
+
+#[test]
+fn test_subtree_query() {
+    //      0
+    //    /   \
+    //   1     2
+    //  / \     \
+    // 3   4     5
+    let values = vec![1u64, 2, 3, 4, 5, 6];
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .add_edge(2, 5)
+        .build(0)
+        .unwrap();
+    
+    assert_eq!(halide.query_subtree(0), 21); // whole tree
+    assert_eq!(halide.query_subtree(1), 2 + 4 + 5);
+    assert_eq!(halide.query_subtree(2), 3 + 6);
+    assert_eq!(halide.query_subtree(4), 5); // leaf
+    
+    assert_eq!(halide.get_subtree_size(0), 6);
+    assert_eq!(halide.get_subtree_size(1), 3);
+    assert_eq!(halide.get_subtree_size(5), 1);
+}
+
+#[test]
+fn test_subtree_update() {
+    let values = vec![1u64, 2, 3, 4, 5, 6];
+    let mut halide = HalideBuilder::with_lazy(values, Sum, RangeAssignSum, RangeAssignSum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .add_edge(2, 5)
+        .build(0)
+        .unwrap();
+    
+    // Assign 10 to every node under 1
+    halide.update_subtree(1, 10);
+    assert_eq!(halide.query_subtree(1), 30);
+    assert_eq!(halide.query_subtree(2), 3 + 6); // untouched
+    assert_eq!(halide.query_subtree(0), 1 + 30 + 3 + 6);
+    
+    // Path queries see the subtree update
+    // Path: 3 -> 1 -> 0 -> 2 -> 5, values: 10, 10, 1, 3, 6
+    assert_eq!(halide.query(3, 5), Some(30));
+}
+
+#[test]
+fn test_from_fn() {
+    // Sum modulo a prime, without declaring a combine type
+    let modulus = 7u64;
+    let values = vec![3u64, 5, 6, 4, 2];
+    let halide = HalideBuilder::from_fn(values, move |a: &u64, b: &u64| (a + b) % modulus, 0)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
+    
+    // Path: 3 -> 1 -> 0 -> 2, values: 4, 5, 3, 6
+    assert_eq!(halide.query(3, 2), Some((4 + 5 + 3 + 6) % 7));
+    assert_eq!(halide.query_subtree(1), (5 + 4 + 2) % 7);
+}
+
+#[test]
+fn test_fn_combine_with_lazy() {
+    // Closure-based max, paired with assignment updates
+    let values = vec![3i32, -5, 6, 4, 2];
+    let max = FnCombine::new(|a: &i32, b: &i32| *a.max(b), i32::MIN);
+    let mut halide = HalideBuilder::with_lazy(values, max, RangeAssignMinMax, RangeAssignMinMax)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
+    
+    assert_eq!(halide.query(3, 4), Some(4));
+    halide.update_subtree(1, -1);
+    assert_eq!(halide.query(3, 4), Some(-1));
+    assert_eq!(halide.query(3, 2), Some(6));
+}
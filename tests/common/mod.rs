@@ -0,0 +1,62 @@
+//! Helpers shared by the integration tests
+//!
+//! Each test file only uses some of these, so unused ones aren't warnings.
+#![allow(dead_code)]
+
+use bipath::{CombineFn, LazyApplyFn, LazyFunc, Tree};
+
+/// Composition of affine maps `x -> a * x + b`, applied left to right, so
+/// path order matters
+#[derive(Clone)]
+pub struct AffineCompose;
+impl CombineFn<(u64, u64)> for AffineCompose {
+    fn combine(&self, f: &(u64, u64), g: &(u64, u64)) -> (u64, u64) {
+        (g.0.wrapping_mul(f.0), g.0.wrapping_mul(f.1).wrapping_add(g.1))
+    }
+
+    fn identity(&self) -> (u64, u64) {
+        (1, 0)
+    }
+}
+
+/// Multiply every element of a summed segment
+pub struct MulToSum;
+impl LazyApplyFn<i64> for MulToSum {
+    fn apply(&self, pending: &i64, new: &i64) -> i64 {
+        pending * new
+    }
+}
+impl LazyFunc<i64> for MulToSum {
+    fn apply(&self, cur_val: &i64, lazy_val: &i64, _l: usize, _r: usize) -> i64 {
+        cur_val * lazy_val
+    }
+}
+
+/// Nodes on the u -> v path, found by walking parent pointers
+pub fn naive_path<T>(tree: &Tree<T>, mut u: usize, mut v: usize) -> Vec<usize> {
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+    while tree.get_depth(u) > tree.get_depth(v) {
+        front.push(u);
+        u = tree.get_parent(u).unwrap();
+    }
+    while tree.get_depth(v) > tree.get_depth(u) {
+        back.push(v);
+        v = tree.get_parent(v).unwrap();
+    }
+    while u != v {
+        front.push(u);
+        back.push(v);
+        u = tree.get_parent(u).unwrap();
+        v = tree.get_parent(v).unwrap();
+    }
+    front.push(u);
+    front.extend(back.into_iter().rev());
+    front
+}
+
+/// Next pseudo-random number from a linear congruential generator
+pub fn next(seed: &mut u64) -> usize {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (*seed >> 33) as usize
+}
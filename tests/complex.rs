@@ -1,39 +1,27 @@
-use halide::{Halide, CombineFn};
+mod common;
 
-#[derive(Clone)]
-struct SumCombine;
-impl CombineFn<u64> for SumCombine {
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a + b
-    }
-}
-
-#[derive(Clone)]
-struct XorCombine;
-impl CombineFn<u64> for XorCombine {
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a ^ b
-    }
-}
+use bipath::{HalideBuilder, CombineFn, Direction};
+use bipath::monoids::{Sum, Xor};
+use common::{naive_path, next, AffineCompose};
 
 #[test]
 fn test_large_tree() {
     // Create a tree with 100 nodes in a chain
     let n = 100;
     let values: Vec<u64> = (1..=n).map(|i| i as u64).collect();
-    let mut halide = Halide::new(values, 7, SumCombine, 0u64);
+    let mut builder = HalideBuilder::new(values, Sum);
     
     for i in 0..n - 1 {
-        halide.add_edge(i, i + 1);
+        builder = builder.add_edge(i, i + 1);
     }
-    halide.init(0);
+    let halide = builder.build(0).unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(0), 0);
     assert_eq!(tree.get_depth(n - 1), n - 1);
     
     // Query from one end to the other
-    let result = halide.query(0, n - 1);
+    let result = halide.query(0, n - 1).unwrap();
     let expected: u64 = (1..=n).map(|i| i as u64).sum();
     assert_eq!(result, expected);
 }
@@ -43,12 +31,12 @@ fn test_deep_tree() {
     // Create a very deep tree (1000 nodes in a chain)
     let n = 1000;
     let values: Vec<u64> = (0..n).map(|i| (i % 100) as u64).collect();
-    let mut halide = Halide::new(values, 10, XorCombine, 0u64);
+    let mut builder = HalideBuilder::new(values, Xor);
     
     for i in 0..n - 1 {
-        halide.add_edge(i, i + 1);
+        builder = builder.add_edge(i, i + 1);
     }
-    halide.init(0);
+    let halide = builder.build(0).unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(0), 0);
@@ -65,13 +53,13 @@ fn test_wide_tree() {
     // Create a wide tree (star with many leaves)
     let n = 50;
     let values: Vec<u64> = (0..n).map(|i| i as u64).collect();
-    let mut halide = Halide::new(values, 6, SumCombine, 0u64);
+    let mut builder = HalideBuilder::new(values, Sum);
     
     // Connect all nodes to node 0
     for i in 1..n {
-        halide.add_edge(0, i);
+        builder = builder.add_edge(0, i);
     }
-    halide.init(0);
+    let halide = builder.build(0).unwrap();
     
     let tree = halide.tree();
     assert_eq!(tree.get_depth(0), 0);
@@ -81,8 +69,8 @@ fn test_wide_tree() {
     }
     
     // Query between two leaves
-    let result = halide.query(1, 2);
-    assert_eq!(result, 0 + 1 + 0 + 2); // root + leaf1 + root + leaf2
+    let result = halide.query(1, 2).unwrap();
+    assert_eq!(result, 1 + 2); // leaf1 + root (0) + leaf2
 }
 
 #[test]
@@ -96,19 +84,19 @@ fn test_complex_topology() {
     //    |     |
     //    9     10
     let values: Vec<u64> = (0..11).map(|i| i as u64).collect();
-    let mut halide = Halide::new(values, 4, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(0, 3);
-    halide.add_edge(1, 4);
-    halide.add_edge(1, 5);
-    halide.add_edge(2, 6);
-    halide.add_edge(3, 7);
-    halide.add_edge(3, 8);
-    halide.add_edge(5, 9);
-    halide.add_edge(7, 10);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(0, 3)
+        .add_edge(1, 4)
+        .add_edge(1, 5)
+        .add_edge(2, 6)
+        .add_edge(3, 7)
+        .add_edge(3, 8)
+        .add_edge(5, 9)
+        .add_edge(7, 10)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     
@@ -120,43 +108,43 @@ fn test_complex_topology() {
     assert_eq!(tree.lca(5, 9), 5);
     
     // Test path queries
-    let q1 = halide.query(4, 6);
+    let q1 = halide.query(4, 6).unwrap();
     // Path: 4 -> 1 -> 0 -> 2 -> 6
-    assert_eq!(q1, 4 + 1 + 0 + 2 + 6);
+    assert_eq!(q1, 4 + 1 + 2 + 6); // root contributes 0
     
-    let q2 = halide.query(9, 10);
+    let q2 = halide.query(9, 10).unwrap();
     // Path: 9 -> 5 -> 1 -> 0 -> 3 -> 7 -> 10
-    assert_eq!(q2, 9 + 5 + 1 + 0 + 3 + 7 + 10);
+    assert_eq!(q2, 9 + 5 + 1 + 3 + 7 + 10); // root contributes 0
 }
 
 #[test]
 fn test_multiple_updates() {
     let values = vec![1u64, 2, 3, 4, 5, 6, 7];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.add_edge(2, 5);
-    halide.add_edge(2, 6);
-    halide.init(0);
+    let mut halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .add_edge(2, 5)
+        .add_edge(2, 6)
+        .build(0)
+        .unwrap();
     
     // First update
     halide.update(3, 4, 10);
-    let q1 = halide.query(3, 4);
+    let q1 = halide.query(3, 4).unwrap();
     // After update, nodes on path should have value 10
     assert!(q1 > 0); // Just verify it's positive after update
     
     // Second update on different path
     halide.update(5, 6, 20);
-    let q2 = halide.query(5, 6);
+    let q2 = halide.query(5, 6).unwrap();
     // After update, nodes on path should have value 20
     assert!(q2 > 0); // Just verify it's positive after update
     
     // Update overlapping path
     halide.update(1, 5, 5);
-    let q3 = halide.query(1, 5);
+    let q3 = halide.query(1, 5).unwrap();
     // Path: 1 -> 0 -> 2 -> 5, all updated to 5
     assert!(q3 > 0); // Just verify it's positive after update
 }
@@ -164,13 +152,13 @@ fn test_multiple_updates() {
 #[test]
 fn test_kth_ancestor_edge_cases() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(1, 2);
-    halide.add_edge(2, 3);
-    halide.add_edge(3, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(1, 2)
+        .add_edge(2, 3)
+        .add_edge(3, 4)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     
@@ -188,67 +176,304 @@ fn test_kth_ancestor_edge_cases() {
 #[test]
 fn test_same_node_queries() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let mut halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
     
     // Query same node
-    let result = halide.query(2, 2);
+    let result = halide.query(2, 2).unwrap();
     assert_eq!(result, 3); // Just the value of node 2
     
     // Update same node
     halide.update(2, 2, 100);
-    let result = halide.query(2, 2);
+    let result = halide.query(2, 2).unwrap();
     assert_eq!(result, 100);
 }
 
 #[test]
 fn test_path_to_root() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(1, 2);
-    halide.add_edge(2, 3);
-    halide.add_edge(3, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(1, 2)
+        .add_edge(2, 3)
+        .add_edge(3, 4)
+        .build(0)
+        .unwrap();
     
     // Query from leaf to root
-    let result = halide.query(4, 0);
+    let result = halide.query(4, 0).unwrap();
     assert_eq!(result, 1 + 2 + 3 + 4 + 5); // 15
     
     // Query from root to leaf (should be same)
-    let result2 = halide.query(0, 4);
+    let result2 = halide.query(0, 4).unwrap();
     assert_eq!(result2, 15);
 }
 
 #[test]
 fn test_different_value_types() {
     // Test with i32
-    #[derive(Clone)]
-    struct I32Sum;
-    impl CombineFn<i32> for I32Sum {
-        fn combine(&self, a: i32, b: i32) -> i32 {
-            a + b
+    
+    let values = vec![1i32, -2, 3, -4, 5];
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
+    
+    let result = halide.query(3, 4).unwrap();
+    // Path: 3 -> 1 -> 4, values: -4, -2, 5
+    // Result depends on query_chain implementation
+    assert!((-10..=10).contains(&result)); // Just verify it's reasonable
+}
+
+
+#[test]
+fn test_query_path_non_commutative() {
+    // Pseudo-random tree so that paths cross several heavy chains
+    let n = 200;
+    let values: Vec<(u64, u64)> = (0..n as u64).map(|i| (i % 7 + 2, i * 13 % 11)).collect();
+    let mut builder = HalideBuilder::new(values.clone(), AffineCompose);
+
+    let mut seed = 12345u64;
+    for i in 1..n {
+        let parent = next(&mut seed) % i;
+        builder = builder.add_edge(parent, i);
+    }
+    let halide = builder.build(0).unwrap();
+
+    for u in (0..n).step_by(7) {
+        for v in (0..n).step_by(11) {
+            let expected = naive_path(halide.tree(), u, v)
+                .into_iter()
+                .fold((1, 0), |acc, x| AffineCompose.combine(&acc, &values[x]));
+            assert_eq!(halide.query_path(u, v), Some(expected), "path {} -> {}", u, v);
+        }
+    }
+}
+
+#[test]
+fn test_query_path_direction() {
+    // 0 - 1 - 2 - 3, each node holding an affine map
+    let values = vec![(2u64, 1u64), (3, 0), (1, 5), (2, 0)];
+    let halide = HalideBuilder::new(values, AffineCompose)
+        .add_edge(0, 1)
+        .add_edge(1, 2)
+        .add_edge(2, 3)
+        .build(0)
+        .unwrap();
+
+    // 3 -> 0: x -> 2x -> 2x + 5 -> 6x + 15 -> 12x + 31
+    assert_eq!(halide.query_path(3, 0), Some((12, 31)));
+    // 0 -> 3: x -> 2x + 1 -> 6x + 3 -> 6x + 8 -> 12x + 16
+    assert_eq!(halide.query_path(0, 3), Some((12, 16)));
+    assert_eq!(halide.query_path(2, 2), Some((1, 5)));
+}
+
+#[test]
+fn test_path_segments() {
+    let n = 300;
+    let mut builder = HalideBuilder::new(vec![0u64; n], Sum);
+    let mut seed = 99u64;
+    for i in 1..n {
+        builder = builder.add_edge(next(&mut seed) % i, i);
+    }
+    let halide = builder.build(0).unwrap();
+
+    // An external array indexed by label, as a Fenwick tree or bitset would be
+    let mut by_label = vec![0; n];
+    for v in 0..n {
+        by_label[halide.get_label(v)] = v;
+    }
+
+    for u in (0..n).step_by(13) {
+        for v in (0..n).step_by(17) {
+            let mut walked = Vec::new();
+            let mut pieces = 0;
+            for (lo, hi, direction) in halide.path_segments(u, v) {
+                assert!(lo <= hi);
+                match direction {
+                    Direction::Up => walked.extend((lo..=hi).rev().map(|l| by_label[l])),
+                    Direction::Down => walked.extend((lo..=hi).map(|l| by_label[l])),
+                }
+                pieces += 1;
+            }
+            assert_eq!(walked, naive_path(halide.tree(), u, v), "path {} -> {}", u, v);
+            assert_eq!(halide.path_nodes(u, v).collect::<Vec<_>>(), walked);
+            assert_eq!(halide.tree().distance(u, v), walked.len() - 1);
+            assert!(pieces <= 2 * (usize::BITS - n.leading_zeros()) as usize + 1);
+        }
+    }
+}
+
+#[test]
+fn test_kth_on_path() {
+    let n = 250;
+    let mut builder = HalideBuilder::new(vec![0u64; n], Sum);
+    let mut seed = 4242u64;
+    for i in 1..n {
+        builder = builder.add_edge(next(&mut seed) % i, i);
+    }
+    let halide = builder.build(0).unwrap();
+    let tree = halide.tree();
+
+    for v in 0..n {
+        for k in 0..=tree.get_depth(v) {
+            assert_eq!(halide.kth_ancestor(v, k), Some(tree.get_kth_ancestor(v, k)));
         }
+        assert_eq!(halide.kth_ancestor(v, tree.get_depth(v) + 1), None);
     }
+
+    for u in (0..n).step_by(11) {
+        for v in (0..n).step_by(7) {
+            let path = naive_path(tree, u, v);
+            for (k, &x) in path.iter().enumerate() {
+                assert_eq!(halide.kth_on_path(u, v, k), Some(x), "{} -> {}, k = {}", u, v, k);
+            }
+            assert_eq!(halide.kth_on_path(u, v, path.len()), None);
+        }
+    }
+}
+
+#[test]
+fn test_rerooted_queries() {
+    // Every rerooted answer must match a Halide actually built at that root
+    let n = 40;
+    let values: Vec<u64> = (0..n as u64).map(|i| i * 7 % 13 + 1).collect();
+    let mut seed = 777u64;
+    let edges: Vec<(usize, usize)> = (1..n)
+        .map(|i| (next(&mut seed) % i, i))
+        .collect();
+    let build = |root| HalideBuilder::new(values.clone(), Sum).edges(edges.clone()).build(root).unwrap();
+    let halide = build(0);
+
+    for r in 0..n {
+        let rooted = build(r);
+        for v in 0..n {
+            assert_eq!(halide.subtree_query_rooted(v, r), rooted.query_subtree(v), "subtree {} at root {}", v, r);
+            assert_eq!(halide.parent_rooted(v, r), rooted.tree().get_parent(v), "parent {} at root {}", v, r);
+        }
+        for u in (0..n).step_by(3) {
+            for v in (0..n).step_by(5) {
+                assert_eq!(halide.lca_rooted(u, v, r), rooted.tree().lca(u, v), "lca {} {} at root {}", u, v, r);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rerooted_edge_valued_subtree() {
+    //  0 -5- 1 -2- 2
+    //        3
+    //        |
+    //        3
+    let halide = HalideBuilder::edge_valued(4, Sum)
+        .weighted_edges([(0, 1, 5u64), (1, 2, 2), (1, 3, 3)])
+        .build(0)
+        .unwrap();
+
+    assert_eq!(halide.subtree_query_rooted(1, 0), 2 + 3);
+    // Rooted at 2, node 1's subtree holds the edges 1 - 0 and 1 - 3
+    assert_eq!(halide.subtree_query_rooted(1, 2), 5 + 3);
+    assert_eq!(halide.subtree_query_rooted(0, 2), 0);
+    assert_eq!(halide.subtree_query_rooted(2, 2), 5 + 2 + 3);
+}
+
+#[test]
+fn test_very_deep_tree() {
+    // A 300k node chain would overflow the stack with recursive initialisation
+    let n = 300_000;
+    let values: Vec<u64> = (0..n).map(|i| i as u64).collect();
+    let mut builder = HalideBuilder::new(values, Sum);
     
-    let values = vec![1i32, -2, 3, -4, 5];
-    let mut halide = Halide::new(values, 3, I32Sum, 0i32);
+    for i in 0..n - 1 {
+        builder = builder.add_edge(i, i + 1);
+    }
+    let halide = builder.build(0).unwrap();
+    
+    let tree = halide.tree();
+    assert_eq!(tree.get_depth(n - 1), n - 1);
+    assert_eq!(tree.lca(n - 1, n / 2), n / 2);
+    assert_eq!(tree.get_kth_ancestor(n - 1, n - 1), 0);
     
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let expected: u64 = (0..n as u64).sum();
+    assert_eq!(halide.query(0, n - 1), Some(expected));
+    assert_eq!(halide.query_subtree(n - 2), (n - 2 + n - 1) as u64);
+}
+
+#[test]
+fn test_lifting_depth_from_tree() {
+    // A chain of 1025 nodes needs 11 lifting levels; nothing has to be guessed
+    let n = 1025;
+    let values: Vec<u64> = vec![1; n];
+    let mut builder = HalideBuilder::new(values, Sum);
     
-    let result = halide.query(3, 4);
-    // Path: 3 -> 1 -> 4, values: -4, -2, 5
-    // Result depends on query_chain implementation
-    assert!(result >= -10 && result <= 10); // Just verify it's reasonable
+    for i in 0..n - 1 {
+        builder = builder.add_edge(i, i + 1);
+    }
+    let halide = builder.build(0).unwrap();
+    
+    let tree = halide.tree();
+    assert_eq!(tree.get_kth_ancestor(n - 1, 1024), 0);
+    assert_eq!(tree.get_kth_ancestor(n - 1, 1025), usize::MAX);
+    assert_eq!(tree.get_kth_ancestor(n - 1, 1 << 20), usize::MAX);
+    assert_eq!(tree.lca(n - 1, 1), 1);
+    
+    for v in [1, 511, 512, 1023, 1024] {
+        assert_eq!(halide.query(v, n - 1), Some((n - v) as u64));
+    }
 }
 
+#[test]
+fn test_string_values() {
+    // Concatenation is order-sensitive and String isn't Copy
+    //     a
+    //    / \
+    //   b   c
+    //  / \
+    // d   e
+    let values: Vec<String> = ["a", "b", "c", "d", "e"].iter().map(|s| s.to_string()).collect();
+    let mut halide = HalideBuilder::from_fn(values, |a: &String, b: &String| format!("{}{}", a, b), String::new())
+        .edges([(0, 1), (0, 2), (1, 3), (1, 4)])
+        .build(0)
+        .unwrap();
+
+    assert_eq!(halide.query_path(3, 2).as_deref(), Some("dbac"));
+    assert_eq!(halide.query_path(2, 4).as_deref(), Some("cabe"));
+
+    halide.set(1, "x".to_string());
+    halide.modify(2, |s| s + "!");
+    assert_eq!(halide.get(2), "c!");
+    assert_eq!(halide.query_path(3, 2).as_deref(), Some("dxac!"));
+    assert_eq!(halide.query_path(4, 3).as_deref(), Some("exd"));
+}
+
+#[test]
+fn test_vec_histogram_values() {
+    // Each node is a one-hot histogram over 3 buckets, summed elementwise
+    let bucket = |b: usize| {
+        let mut h = vec![0u32; 3];
+        h[b] = 1;
+        h
+    };
+    let values: Vec<Vec<u32>> = (0..6).map(|v| bucket(v % 3)).collect();
+    let add = |a: &Vec<u32>, b: &Vec<u32>| a.iter().zip(b).map(|(x, y)| x + y).collect();
+    let mut halide = HalideBuilder::from_fn(values, add, vec![0u32; 3])
+        .edges([(0, 1), (1, 2), (2, 3), (0, 4), (4, 5)])
+        .build(0)
+        .unwrap();
+
+    assert_eq!(halide.query(3, 5), Some(vec![2, 2, 2]));
+    assert_eq!(halide.query_subtree(1), vec![1, 1, 1]);
+
+    halide.set(5, bucket(0));
+    halide.modify(4, |h| add(&h, &bucket(0)));
+    assert_eq!(halide.query(3, 5), Some(vec![4, 2, 1]));
+    assert_eq!(halide.query_subtree(4), vec![2, 1, 0]);
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use bipath::{HalideBuilder, HalideError, RangeAddSum, Tree};
+use bipath::monoids::{Max, Sum};
+use common::next;
+
+/// Child endpoints of the edges on the u -> v path, in order
+fn naive_path_edges<T>(tree: &Tree<T>, mut u: usize, mut v: usize) -> Vec<usize> {
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+    while u != v {
+        if tree.get_depth(u) >= tree.get_depth(v) {
+            front.push(u);
+            u = tree.get_parent(u).unwrap();
+        } else {
+            back.push(v);
+            v = tree.get_parent(v).unwrap();
+        }
+    }
+    front.extend(back.into_iter().rev());
+    front
+}
+
+#[test]
+fn test_weighted_path() {
+    //      0
+    //   3 / \ 7
+    //    1   2
+    // 2 / \ 5
+    //  3   4
+    let halide = HalideBuilder::edge_valued(5, Sum)
+        .add_weighted_edge(0, 1, 3u64)
+        .add_weighted_edge(2, 0, 7)
+        .add_weighted_edge(1, 3, 2)
+        .add_weighted_edge(4, 1, 5)
+        .build(0)
+        .unwrap();
+
+    assert!(halide.is_edge_valued());
+    assert_eq!(halide.query(3, 4), Some(2 + 5));
+    assert_eq!(halide.query_path(3, 2), Some(2 + 3 + 7));
+    assert_eq!(halide.query(1, 0), Some(3));
+    assert_eq!(halide.query(4, 4), Some(0));
+    assert_eq!(halide.query_path(2, 2), Some(0));
+    assert_eq!(halide.query_subtree(1), 2 + 5);
+    assert_eq!(halide.query_subtree(3), 0);
+
+    // The LCA is still a node of the path, even though its slot is skipped
+    assert_eq!(halide.path_nodes(3, 2).collect::<Vec<_>>(), vec![3, 1, 0, 2]);
+    assert_eq!(halide.path_segments(4, 4).count(), 0);
+    assert_eq!(halide.path_nodes(4, 4).collect::<Vec<_>>(), vec![4]);
+}
+
+#[test]
+fn test_weighted_update_skips_lca() {
+    let mut halide = HalideBuilder::edge_valued(5, Max)
+        .weighted_edges([(0, 1, 3i32), (0, 2, 7), (1, 3, 2), (1, 4, 5)])
+        .build(0)
+        .unwrap();
+
+    // Assigning along 3 - 4 must leave the edge 0 - 1 above their LCA alone
+    halide.update(3, 4, 1);
+    assert_eq!(halide.query(3, 4), Some(1));
+    assert_eq!(halide.query(1, 0), Some(3));
+    assert_eq!(halide.query(3, 2), Some(7));
+}
+
+#[test]
+fn test_values_on_edges_with_lazy() {
+    // Node values stand for the edge to the parent, with range-add updates
+    //   0 -9- 1 -2- 2
+    let mut halide = HalideBuilder::with_lazy(vec![9u64, 9, 2], Sum, RangeAddSum, RangeAddSum)
+        .values_on_edges()
+        .edges([(0, 1), (1, 2)])
+        .build(0)
+        .unwrap();
+
+    assert!(halide.is_edge_valued());
+    assert_eq!(halide.get(0), 0);
+    assert_eq!(halide.query(0, 2), Some(9 + 2));
+    assert_eq!(halide.query_subtree(1), 2);
+
+    halide.update(2, 0, 10);
+    assert_eq!(halide.query(0, 2), Some(19 + 12));
+    assert_eq!(halide.get(0), 0);
+}
+
+#[test]
+fn test_set_root_of_edge_valued() {
+    let mut halide = HalideBuilder::edge_valued(2, Sum)
+        .weighted_edges([(0, 1, 5u64)])
+        .build(0)
+        .unwrap();
+
+    assert_eq!(halide.try_set(0, 3), Err(HalideError::RootHasNoEdge(0)));
+    assert_eq!(halide.try_modify(0, |x| x + 1), Err(HalideError::RootHasNoEdge(0)));
+    assert_eq!(halide.try_set(1, 3), Ok(()));
+    assert_eq!(halide.query(0, 1), Some(3));
+}
+
+#[test]
+#[should_panic(expected = "node 0 is a root")]
+fn test_set_root_of_edge_valued_panics() {
+    let mut halide = HalideBuilder::edge_valued(2, Sum)
+        .weighted_edges([(0, 1, 5u64)])
+        .build(0)
+        .unwrap();
+    halide.set(0, 3);
+}
+
+#[test]
+fn test_weighted_root_choice() {
+    // The same edges rooted elsewhere must give the same path costs
+    let edges = [(0, 1, 4u64), (1, 2, 1), (1, 3, 6), (3, 4, 2), (3, 5, 9)];
+    let a = HalideBuilder::edge_valued(6, Sum).weighted_edges(edges).build(0).unwrap();
+    let b = HalideBuilder::edge_valued(6, Sum).weighted_edges(edges).build(4).unwrap();
+
+    for u in 0..6 {
+        for v in 0..6 {
+            assert_eq!(a.query(u, v), b.query(u, v), "path {} - {}", u, v);
+        }
+    }
+    assert_eq!(b.query(0, 5), Some(4 + 6 + 9));
+}
+
+#[test]
+fn test_weighted_against_naive() {
+    let n = 150;
+    let mut seed = 5u64;
+
+    let mut builder = HalideBuilder::with_lazy(vec![0i64; n], Sum, RangeAddSum, RangeAddSum);
+    for v in 1..n {
+        let w = (next(&mut seed) % 19) as i64 - 9;
+        builder = builder.add_weighted_edge(next(&mut seed) % v, v, w);
+    }
+    let mut halide = builder.build(0).unwrap();
+
+    let mut naive: Vec<i64> = (0..n).map(|v| halide.get_node(v).map_or(0, |node| *node.value())).collect();
+    assert_eq!(naive[0], 0);
+
+    for step in 0..100 {
+        let (u, v) = (next(&mut seed) % n, next(&mut seed) % n);
+        let delta = step as i64 % 7 - 3;
+        halide.update(u, v, delta);
+        for x in naive_path_edges(halide.tree(), u, v) {
+            naive[x] += delta;
+        }
+
+        let (a, b) = (next(&mut seed) % n, next(&mut seed) % n);
+        let expected: i64 = naive_path_edges(halide.tree(), a, b).into_iter().map(|x| naive[x]).sum();
+        assert_eq!(halide.query(a, b), Some(expected), "path {} - {} at step {}", a, b, step);
+        assert_eq!(halide.query_path(a, b), Some(expected), "path {} - {} at step {}", a, b, step);
+    }
+}
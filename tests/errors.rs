@@ -0,0 +1,116 @@
+use bipath::{HalideBuilder, HalideError};
+use bipath::monoids::Sum;
+
+fn five_nodes() -> HalideBuilder<u64, Sum> {
+    HalideBuilder::new(vec![1u64, 2, 3, 4, 5], Sum)
+}
+
+#[test]
+fn test_invalid_edge() {
+    let build = |u, v| five_nodes().edges([(0, 1), (u, v)]).build(0).err();
+
+    assert_eq!(build(0, 5), Some(HalideError::InvalidNode { id: 5, n: 5 }));
+    assert_eq!(build(7, 1), Some(HalideError::InvalidNode { id: 7, n: 5 }));
+    assert_eq!(build(2, 2), Some(HalideError::SelfLoop(2)));
+}
+
+#[test]
+fn test_valid_tree() {
+    let mut halide = five_nodes()
+        .edges([(0, 1), (0, 2), (1, 3), (1, 4)])
+        .build(0)
+        .unwrap();
+
+    assert_eq!(halide.try_query(3, 4), Ok(4 + 2 + 5));
+    assert_eq!(halide.try_query_path(3, 2), Ok(4 + 2 + 1 + 3));
+    assert_eq!(halide.try_query_subtree(1), Ok(2 + 4 + 5));
+    assert_eq!(halide.try_update(3, 4, 0), Ok(()));
+    assert_eq!(halide.try_update_subtree(2, 0), Ok(()));
+    assert_eq!(halide.try_query_subtree(0), Ok(1));
+    assert_eq!(halide.tree().try_lca(3, 4), Ok(1));
+}
+
+#[test]
+fn test_from_parents() {
+    //    0
+    //   / \
+    //  1   2
+    //  |
+    //  3 - 4 (4's parent is 3)
+    let parents = [None, Some(0), Some(0), Some(1), Some(3)];
+    let halide = five_nodes().parents(&parents).build(0).unwrap();
+
+    assert_eq!(halide.tree().get_parent(4), Some(3));
+    assert_eq!(halide.query(4, 2), Some(5 + 4 + 2 + 1 + 3));
+}
+
+#[test]
+fn test_incremental_edges() {
+    let mut builder = five_nodes().add_edge(0, 1).add_edge(1, 2);
+    for v in 3..5 {
+        builder = builder.add_edge(2, v);
+    }
+    let halide = builder.build(0).unwrap();
+
+    assert_eq!(halide.query(3, 4), Some(4 + 3 + 5));
+}
+
+#[test]
+fn test_cycle() {
+    let err = five_nodes()
+        .edges([(0, 1), (1, 2), (2, 0), (3, 4)])
+        .build(0)
+        .err();
+
+    assert!(matches!(err, Some(HalideError::Cycle(_, _))));
+}
+
+#[test]
+fn test_duplicate_edge() {
+    let err = five_nodes()
+        .edges([(0, 1), (1, 2), (2, 3), (3, 4), (2, 3)])
+        .build(0)
+        .err()
+        .unwrap();
+
+    assert!(err == HalideError::DuplicateEdge(2, 3) || err == HalideError::DuplicateEdge(3, 2));
+}
+
+#[test]
+fn test_disconnected() {
+    let err = five_nodes().edges([(0, 1), (1, 2), (3, 4)]).build(0).err();
+    assert_eq!(err, Some(HalideError::Disconnected { reached: 3, n: 5 }));
+}
+
+#[test]
+fn test_invalid_root() {
+    let err = five_nodes().edges([(0, 1), (1, 2), (2, 3), (3, 4)]).build(9).err();
+    assert_eq!(err, Some(HalideError::InvalidNode { id: 9, n: 5 }));
+}
+
+#[test]
+fn test_query_invalid_node() {
+    let mut halide = five_nodes()
+        .edges([(0, 1), (0, 2), (1, 3), (1, 4)])
+        .build(0)
+        .unwrap();
+
+    assert_eq!(halide.try_query(0, 10), Err(HalideError::InvalidNode { id: 10, n: 5 }));
+    assert_eq!(halide.try_update_subtree(5, 1), Err(HalideError::InvalidNode { id: 5, n: 5 }));
+    assert_eq!(halide.tree().try_lca(6, 0), Err(HalideError::InvalidNode { id: 6, n: 5 }));
+}
+
+#[test]
+fn test_empty_tree() {
+    let err = HalideBuilder::new(Vec::<u64>::new(), Sum).build(0).err();
+    assert_eq!(err, Some(HalideError::EmptyTree));
+}
+
+#[test]
+fn test_error_display() {
+    assert_eq!(
+        HalideError::Disconnected { reached: 3, n: 5 }.to_string(),
+        "only 3 of 5 nodes are connected to the root"
+    );
+    assert_eq!(HalideError::Cycle(1, 2).to_string(), "edge (1, 2) closes a cycle");
+}
@@ -0,0 +1,100 @@
+use bipath::{HalideBuilder, HalideError, RangeAssignSum};
+use bipath::monoids::Sum;
+
+/// Two trees and an isolated node:
+///
+/// ```text
+///   0       3     6
+///  / \     / \
+/// 1   2   4   5
+/// ```
+fn forest() -> HalideBuilder<u64, Sum, RangeAssignSum, RangeAssignSum> {
+    HalideBuilder::with_lazy(vec![1, 2, 3, 4, 5, 6, 7], Sum, RangeAssignSum, RangeAssignSum)
+        .edges([(0, 1), (0, 2), (4, 3), (3, 5)])
+}
+
+#[test]
+fn test_components() {
+    let halide = forest().build_forest().unwrap();
+    let tree = halide.tree();
+
+    assert_eq!(tree.roots(), &[0, 3, 6]);
+    assert_eq!((0..7).map(|v| halide.component_of(v)).collect::<Vec<_>>(), vec![0, 0, 0, 3, 3, 3, 6]);
+    assert!(tree.connected(4, 5));
+    assert!(!tree.connected(2, 4));
+    assert_eq!(tree.get_parent(4), Some(3));
+    assert_eq!(tree.get_parent(6), None);
+}
+
+#[test]
+fn test_queries_within_and_across_trees() {
+    let mut halide = forest().build_forest().unwrap();
+
+    assert_eq!(halide.query(1, 2), Some(2 + 1 + 3));
+    assert_eq!(halide.query_path(4, 5), Some(5 + 4 + 6));
+    assert_eq!(halide.query(6, 6), Some(7));
+    assert_eq!(halide.query(1, 4), None);
+    assert_eq!(halide.query_path(5, 6), None);
+    assert_eq!(halide.kth_on_path(1, 4, 0), None);
+    assert_eq!(halide.path_find_first(0, 3, |_| true), None);
+
+    halide.update(4, 5, 10);
+    halide.update_subtree(0, 100);
+    assert_eq!(halide.query_subtree(3), 10 * 3);
+    assert_eq!(halide.query_subtree(0), 300);
+    assert_eq!(halide.get(6), 7);
+}
+
+#[test]
+fn test_rerooting_stays_in_component() {
+    let halide = forest().build_forest().unwrap();
+
+    assert_eq!(halide.subtree_query_rooted(3, 3), 4 + 5 + 6);
+    assert_eq!(halide.subtree_query_rooted(3, 5), 4 + 5);
+    assert_eq!(halide.subtree_query_rooted(0, 1), 1 + 3);
+    assert_eq!(halide.subtree_query_rooted(0, 4), 1 + 2 + 3);
+}
+
+#[test]
+fn test_errors_across_trees() {
+    let mut halide = forest().build_forest().unwrap();
+
+    assert_eq!(halide.try_query(2, 5), Err(HalideError::DifferentTrees(2, 5)));
+    assert_eq!(halide.try_query_path(6, 0), Err(HalideError::DifferentTrees(6, 0)));
+    assert_eq!(halide.try_update(1, 3, 0), Err(HalideError::DifferentTrees(1, 3)));
+    assert_eq!(halide.tree().try_lca(0, 6), Err(HalideError::DifferentTrees(0, 6)));
+    assert_eq!(halide.tree().try_distance(4, 5), Ok(2));
+    assert_eq!(halide.try_query(1, 2), Ok(6));
+}
+
+#[test]
+#[should_panic(expected = "same tree")]
+fn test_update_across_trees_panics() {
+    let mut halide = forest().build_forest().unwrap();
+    halide.update(2, 5, 1);
+}
+
+#[test]
+fn test_forest_still_rejects_cycles() {
+    let err = HalideBuilder::new(vec![0u64; 6], Sum)
+        .edges([(0, 1), (3, 4), (4, 5), (5, 3)])
+        .build_forest()
+        .err();
+    assert!(matches!(err, Some(HalideError::Cycle(_, _))));
+
+    // A plain build still insists on a single tree
+    let err = forest().build(0).err();
+    assert_eq!(err, Some(HalideError::Disconnected { reached: 3, n: 7 }));
+}
+
+#[test]
+fn test_edge_valued_forest() {
+    let halide = HalideBuilder::edge_valued(5, Sum)
+        .weighted_edges([(0, 1, 4u64), (2, 3, 7), (3, 4, 1)])
+        .build_forest()
+        .unwrap();
+
+    assert_eq!(halide.query(0, 1), Some(4));
+    assert_eq!(halide.query(2, 4), Some(8));
+    assert_eq!(halide.query(1, 4), None);
+}
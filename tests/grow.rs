@@ -0,0 +1,150 @@
+mod common;
+
+use bipath::{CombineFn, HalideBuilder, HalideError, RangeAddSum, Tree};
+use bipath::monoids::Sum;
+use common::{naive_path, next, AffineCompose};
+
+/// Whether a is b or one of its ancestors
+fn naive_is_ancestor<T>(tree: &Tree<T>, a: usize, mut b: usize) -> bool {
+    loop {
+        if a == b {
+            return true;
+        }
+        match tree.get_parent(b) {
+            Some(p) => b = p,
+            None => return false,
+        }
+    }
+}
+
+#[test]
+fn test_add_leaf_small() {
+    //     0
+    //    / \
+    //   1   2
+    let mut halide = HalideBuilder::with_lazy(vec![1u64, 2, 3], Sum, RangeAddSum, RangeAddSum)
+        .edges([(0, 1), (0, 2)])
+        .build(0)
+        .unwrap();
+
+    let a = halide.add_leaf(1, 10);
+    let b = halide.add_leaf(a, 20);
+    assert_eq!((a, b), (3, 4));
+    assert_eq!(halide.tree().len(), 5);
+    assert_eq!(halide.tree().get_depth(b), 3);
+    assert_eq!(halide.tree().lca(b, 2), 0);
+
+    assert_eq!(halide.query(b, 2), Some(20 + 10 + 2 + 1 + 3));
+    assert_eq!(halide.path_nodes(b, 2).collect::<Vec<_>>(), vec![4, 3, 1, 0, 2]);
+    assert_eq!(halide.kth_ancestor(b, 3), Some(0));
+    assert_eq!(halide.query_subtree(1), 2 + 10 + 20);
+    assert_eq!(halide.query_subtree(a), 10 + 20);
+    assert_eq!(halide.get_subtree_size(0), 5);
+    assert_eq!(halide.get_subtree_size(a), 2);
+
+    halide.update(b, 2, 1);
+    halide.update_subtree(1, 100);
+    assert_eq!(halide.get(b), 20 + 1 + 100);
+    assert_eq!(halide.get(2), 3 + 1);
+    assert_eq!(halide.query_subtree(0), 2 + 103 + 4 + 111 + 121);
+
+    // Rebuilding keeps every value, including pending updates
+    halide.rebuild();
+    assert_eq!(halide.query_subtree(0), 2 + 103 + 4 + 111 + 121);
+    assert_eq!(halide.get(b), 121);
+    assert_eq!(halide.get_subtree_size(a), 2);
+}
+
+#[test]
+fn test_add_leaf_against_naive() {
+    let mut values: Vec<(u64, u64)> = vec![(2, 1)];
+    let mut halide = HalideBuilder::new(values.clone(), AffineCompose).build(0).unwrap();
+
+    // Growing to 300 nodes forces several rebuilds along the way
+    let mut seed = 777u64;
+    for i in 1..300 {
+        let parent = next(&mut seed) % i;
+        let val = ((i % 5 + 2) as u64, (i * 7 % 13) as u64);
+        assert_eq!(halide.add_leaf(parent, val), i);
+        values.push(val);
+
+        let n = i + 1;
+        let (u, v) = (next(&mut seed) % n, next(&mut seed) % n);
+        let expected = naive_path(halide.tree(), u, v)
+            .into_iter()
+            .fold((1, 0), |acc, x| AffineCompose.combine(&acc, &values[x]));
+        assert_eq!(halide.query_path(u, v), Some(expected), "path {} -> {}", u, v);
+        assert_eq!(halide.path_nodes(u, v).collect::<Vec<_>>(), naive_path(halide.tree(), u, v));
+    }
+}
+
+#[test]
+fn test_updates_between_rebuilds() {
+    let mut values = vec![0i64; 20];
+    let mut builder = HalideBuilder::with_lazy(values.clone(), Sum, RangeAddSum, RangeAddSum);
+    for v in 1..20 {
+        builder = builder.add_edge(v / 2, v);
+    }
+    let mut halide = builder.build(0).unwrap();
+
+    let mut seed = 4242u64;
+    for i in 20..200 {
+        let parent = next(&mut seed) % i;
+        let val = (next(&mut seed) % 10) as i64;
+        halide.add_leaf(parent, val);
+        values.push(val);
+
+        let n = i + 1;
+        let (u, v, w) = (next(&mut seed) % n, next(&mut seed) % n, next(&mut seed) % n);
+        let delta = (next(&mut seed) % 5) as i64;
+        halide.update(u, v, delta);
+        for x in naive_path(halide.tree(), u, v) {
+            values[x] += delta;
+        }
+        halide.update_subtree(w, delta);
+        for (x, val) in values.iter_mut().enumerate() {
+            if naive_is_ancestor(halide.tree(), w, x) {
+                *val += delta;
+            }
+        }
+
+        let r = next(&mut seed) % n;
+        let subtree: i64 = (0..n).filter(|&x| naive_is_ancestor(halide.tree(), w, x)).map(|x| values[x]).sum();
+        assert_eq!(halide.query_subtree(w), subtree);
+        assert_eq!(halide.get_subtree_size(w), (0..n).filter(|&x| naive_is_ancestor(halide.tree(), w, x)).count());
+
+        // Rerooted at r, the subtree of w is everything not on r's side of w
+        let rooted: i64 = (0..n).filter(|&x| halide.lca_rooted(x, w, r) == w).map(|x| values[x]).sum();
+        assert_eq!(halide.subtree_query_rooted(w, r), rooted, "subtree of {} rooted at {}", w, r);
+    }
+}
+
+#[test]
+fn test_add_leaf_edge_valued_forest() {
+    //   0     2
+    // 4 |
+    //   1
+    let mut halide = HalideBuilder::edge_valued(3, Sum)
+        .weighted_edges([(0, 1, 4u64)])
+        .build_forest()
+        .unwrap();
+
+    let a = halide.add_leaf(1, 6);
+    let b = halide.add_leaf(2, 9);
+    assert_eq!(halide.query(a, 0), Some(6 + 4));
+    assert_eq!(halide.query(a, 1), Some(6));
+    assert_eq!(halide.query(b, 2), Some(9));
+    assert_eq!(halide.query(a, b), None);
+    assert_eq!(halide.component_of(b), 2);
+    assert_eq!(halide.query_subtree(1), 6);
+    assert_eq!(halide.query_subtree(a), 0);
+}
+
+#[test]
+fn test_try_add_leaf() {
+    let mut halide = HalideBuilder::new(vec![1u64, 2], Sum).edges([(0, 1)]).build(0).unwrap();
+
+    assert_eq!(halide.try_add_leaf(2, 5), Err(HalideError::InvalidNode { id: 2, n: 2 }));
+    assert_eq!(halide.try_add_leaf(1, 5), Ok(2));
+    assert_eq!(halide.try_query(2, 0), Ok(8));
+}
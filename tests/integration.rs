@@ -1,23 +1,16 @@
-use halide::{Halide, CombineFn, Tree, Node};
-
-#[derive(Clone)]
-struct SumCombine;
-impl CombineFn<u64> for SumCombine {
-    fn combine(&self, a: u64, b: u64) -> u64 {
-        a + b
-    }
-}
+use bipath::{HalideBuilder, HalideError, RangeAddSum};
+use bipath::monoids::Sum;
 
 #[test]
 fn test_tree_access() {
     let values = vec![10u64, 20, 30, 40, 50];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
     
     // Test accessing tree through halide
     let tree = halide.tree();
@@ -45,37 +38,62 @@ fn test_tree_access() {
 #[test]
 fn test_node_mutation() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let mut halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
     
     // Modify a node's value
-    if let Some(node) = halide.get_node_mut(2) {
-        node.set_value(100);
-    }
+    halide.set(2, 100);
     
-    // Verify the change
+    // Both the node and every query see the change
     let node = halide.get_node(2);
     assert_eq!(*node.unwrap().value(), 100);
-    
-    // Note: This doesn't update the segment tree, so queries won't reflect the change
-    // unless we reinitialize or update through the HLD interface
+    assert_eq!(halide.get(2), 100);
+    assert_eq!(halide.query(3, 2), Some(4 + 2 + 1 + 100));
+    assert_eq!(halide.query_subtree(0), 1 + 2 + 100 + 4 + 5);
+
+    halide.modify(3, |x| x * 10);
+    assert_eq!(halide.get(3), 40);
+    assert_eq!(halide.query(3, 4), Some(40 + 2 + 5));
+}
+
+#[test]
+fn test_point_get_after_range_updates() {
+    let mut halide = HalideBuilder::with_lazy(vec![1i64, 2, 3, 4, 5], Sum, RangeAddSum, RangeAddSum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
+
+    halide.update(3, 2, 10);
+    halide.update_subtree(1, 1);
+    assert_eq!(halide.get(3), 4 + 10 + 1);
+    assert_eq!(halide.get(4), 5 + 1);
+
+    // A set replaces whatever pending updates covered the node
+    halide.set(1, 0);
+    halide.modify(0, |x| -x);
+    assert_eq!(halide.get(0), -11);
+    assert_eq!(halide.query(3, 2), Some(15 - 11 + 13));
+    assert_eq!(halide.try_get(7), Err(HalideError::InvalidNode { id: 7, n: 5 }));
 }
 
 #[test]
 fn test_label_access() {
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
     
     // Get labels for nodes
     let label0 = halide.get_label(0);
@@ -96,21 +114,20 @@ fn test_label_access() {
 fn test_complete_workflow() {
     // Simulate a complete workflow: create, build, query, update
     let values: Vec<u64> = (1..=10).collect();
-    let mut halide = Halide::new(values, 4, SumCombine, 0u64);
-    
-    // Build a tree: 0 is root, connects to 1,2,3; 1 connects to 4,5; etc.
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(0, 3);
-    halide.add_edge(1, 4);
-    halide.add_edge(1, 5);
-    halide.add_edge(2, 6);
-    halide.add_edge(2, 7);
-    halide.add_edge(3, 8);
-    halide.add_edge(3, 9);
-    
-    // Initialize
-    halide.init(0);
+    let mut halide = HalideBuilder::new(values, Sum)
+        // Build a tree: 0 is root, connects to 1,2,3; 1 connects to 4,5; etc.
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(0, 3)
+        .add_edge(1, 4)
+        .add_edge(1, 5)
+        .add_edge(2, 6)
+        .add_edge(2, 7)
+        .add_edge(3, 8)
+        .add_edge(3, 9)
+        // Initialize
+        .build(0)
+        .unwrap();
     
     // Verify tree structure
     let tree = halide.tree();
@@ -119,24 +136,24 @@ fn test_complete_workflow() {
     assert_eq!(tree.get_depth(4), 2);
     
     // Perform queries
-    let q1 = halide.query(4, 5);
+    let q1 = halide.query(4, 5).unwrap();
     // Path: 4 -> 1 -> 5, values: 5, 2, 6
     // Result depends on query_chain implementation
     assert!(q1 > 0); // Just verify it's positive
     
-    let q2 = halide.query(6, 9);
+    let q2 = halide.query(6, 9).unwrap();
     // Path: 6 -> 2 -> 0 -> 3 -> 9
     // Values on path: 7, 3, 1, 4, 10
     assert!(q2 > 0); // Just verify it's positive
     
     // Perform updates
     halide.update(4, 5, 100);
-    let q3 = halide.query(4, 5);
+    let q3 = halide.query(4, 5).unwrap();
     // After update, nodes on path should have value 100
     assert!(q3 > 0); // Just verify it's positive after update
     
     // Query after update
-    let q4 = halide.query(0, 4);
+    let q4 = halide.query(0, 4).unwrap();
     // Path: 0 -> 1 -> 4, updated nodes have value 100
     assert!(q4 > 0); // Just verify it's positive
 }
@@ -144,24 +161,24 @@ fn test_complete_workflow() {
 #[test]
 fn test_lca_variations() {
     let values = vec![1u64, 2, 3, 4, 5, 6, 7, 8];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    // Build tree:
-    //       0
-    //      / \
-    //     1   2
-    //    /|   |\
-    //   3 4   5 6
-    //         |
-    //         7
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.add_edge(2, 5);
-    halide.add_edge(2, 6);
-    halide.add_edge(5, 7);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        // Build tree:
+        //       0
+        //      / \
+        //     1   2
+        //    /|   |\
+        //   3 4   5 6
+        //         |
+        //         7
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .add_edge(2, 5)
+        .add_edge(2, 6)
+        .add_edge(5, 7)
+        .build(0)
+        .unwrap();
     
     let tree = halide.tree();
     
@@ -189,26 +206,26 @@ fn test_lca_variations() {
 fn test_query_consistency() {
     // Test that query(u, v) == query(v, u) for symmetric operations
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide.add_edge(0, 1);
-    halide.add_edge(0, 2);
-    halide.add_edge(1, 3);
-    halide.add_edge(1, 4);
-    halide.init(0);
+    let halide = HalideBuilder::new(values, Sum)
+        .add_edge(0, 1)
+        .add_edge(0, 2)
+        .add_edge(1, 3)
+        .add_edge(1, 4)
+        .build(0)
+        .unwrap();
     
     // Sum is symmetric, so query should be symmetric
-    let q1 = halide.query(3, 4);
-    let q2 = halide.query(4, 3);
+    let q1 = halide.query(3, 4).unwrap();
+    let q2 = halide.query(4, 3).unwrap();
     assert_eq!(q1, q2);
     
     // Test with different pairs
-    let q3 = halide.query(0, 3);
-    let q4 = halide.query(3, 0);
+    let q3 = halide.query(0, 3).unwrap();
+    let q4 = halide.query(3, 0).unwrap();
     assert_eq!(q3, q4);
     
-    let q5 = halide.query(2, 4);
-    let q6 = halide.query(4, 2);
+    let q5 = halide.query(2, 4).unwrap();
+    let q6 = halide.query(4, 2).unwrap();
     assert_eq!(q5, q6);
 }
 
@@ -216,26 +233,47 @@ fn test_query_consistency() {
 fn test_update_consistency() {
     // Test that update(u, v, val) affects the same path as update(v, u, val)
     let values = vec![1u64, 2, 3, 4, 5];
-    let mut halide1 = Halide::new(values.clone(), 3, SumCombine, 0u64);
-    let mut halide2 = Halide::new(values, 3, SumCombine, 0u64);
-    
-    halide1.add_edge(0, 1);
-    halide1.add_edge(0, 2);
-    halide1.add_edge(1, 3);
-    halide1.add_edge(1, 4);
-    halide1.init(0);
-    
-    halide2.add_edge(0, 1);
-    halide2.add_edge(0, 2);
-    halide2.add_edge(1, 3);
-    halide2.add_edge(1, 4);
-    halide2.init(0);
+    let edges = [(0, 1), (0, 2), (1, 3), (1, 4)];
+    let mut halide1 = HalideBuilder::new(values.clone(), Sum).edges(edges).build(0).unwrap();
+    let mut halide2 = HalideBuilder::new(values, Sum).edges(edges).build(0).unwrap();
     
     halide1.update(3, 4, 10);
     halide2.update(4, 3, 10);
     
-    let q1 = halide1.query(3, 4);
-    let q2 = halide2.query(3, 4);
+    let q1 = halide1.query(3, 4).unwrap();
+    let q2 = halide2.query(3, 4).unwrap();
     assert_eq!(q1, q2);
 }
 
+
+#[test]
+fn test_concurrent_queries() {
+    let n = 200;
+    let mut builder = HalideBuilder::with_lazy(vec![1i64; n], Sum, RangeAddSum, RangeAddSum);
+    for v in 1..n {
+        builder = builder.add_edge((v - 1) / 3, v);
+    }
+    let mut halide = builder.build(0).unwrap();
+
+    // Leave pending updates in the segment tree, which reads must not disturb
+    halide.update(n - 1, n / 2, 5);
+    halide.update_subtree(1, 2);
+    let expected: Vec<i64> = (0..n).map(|v| halide.query(0, v).unwrap()).collect();
+
+    let halide = &halide;
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let expected = &expected;
+            s.spawn(move || {
+                for v in (t..n).step_by(4) {
+                    assert_eq!(halide.query(0, v), Some(expected[v]));
+                    assert_eq!(halide.query_path(v, 0), Some(expected[v]));
+                }
+            });
+        }
+    });
+
+    // A borrow of the tree can be held across queries
+    let tree = halide.tree();
+    assert_eq!(halide.query(tree.lca(5, 7), 0), Some(expected[tree.lca(5, 7)]));
+}
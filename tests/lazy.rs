@@ -0,0 +1,296 @@
+mod common;
+
+use bipath::{Halide, HalideBuilder, CombineFn, LazyApplyFn, LazyFunc, Tree};
+use bipath::{RangeAddSum, RangeAddMinMax, RangeAssignSum, RangeAssignMinMax, RangeAffineSum};
+use bipath::monoids::{Max, Min, Sum};
+use common::{naive_path, next, MulToSum};
+use std::fmt::Debug;
+
+/// Replace every node x of a segment with min(x, c), aggregating by max
+struct ChminToMax;
+impl LazyApplyFn<i64> for ChminToMax {
+    fn apply(&self, pending: &i64, new: &i64) -> i64 {
+        *pending.min(new)
+    }
+}
+impl LazyFunc<i64> for ChminToMax {
+    fn apply(&self, cur_val: &i64, lazy_val: &i64, _l: usize, _r: usize) -> i64 {
+        *cur_val.min(lazy_val)
+    }
+}
+
+/// Add an `i64` delta to every node of a `(sum, min, count)` aggregate
+struct AddToStats;
+impl LazyApplyFn<i64> for AddToStats {
+    fn apply(&self, pending: &i64, new: &i64) -> i64 {
+        pending + new
+    }
+}
+impl LazyFunc<(i64, i64, i64), i64> for AddToStats {
+    fn apply(&self, cur_val: &(i64, i64, i64), lazy_val: &i64, _l: usize, _r: usize) -> (i64, i64, i64) {
+        let (sum, min, count) = *cur_val;
+        (sum + lazy_val * count, min + lazy_val, count)
+    }
+}
+
+/// Nodes in the subtree of v, found by walking parent pointers
+fn naive_subtree<T>(tree: &Tree<T>, n: usize, v: usize) -> Vec<usize> {
+    (0..n)
+        .filter(|&x| {
+            let mut cur = Some(x);
+            while let Some(c) = cur {
+                if c == v {
+                    return true;
+                }
+                cur = tree.get_parent(c);
+            }
+            false
+        })
+        .collect()
+}
+
+/// Parent of each node in a pseudo-random tree rooted at 0
+fn random_parents(n: usize, mut seed: u64) -> Vec<usize> {
+    (1..n)
+        .map(|i| next(&mut seed) % i)
+        .collect()
+}
+
+#[test]
+fn test_add_along_path() {
+    let n = 120;
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| i % 9 - 4).collect();
+    let mut builder = HalideBuilder::with_lazy(naive.clone(), Sum, RangeAddSum, RangeAddSum);
+    for (i, p) in random_parents(n, 7).into_iter().enumerate() {
+        builder = builder.add_edge(p, i + 1);
+    }
+    let mut halide = builder.build(0).unwrap();
+
+    for step in 0..60 {
+        let (u, v) = ((step * 37) % n, (step * 53 + 11) % n);
+        let delta = step as i64 % 5 - 2;
+        halide.update(u, v, delta);
+        for x in naive_path(halide.tree(), u, v) {
+            naive[x] += delta;
+        }
+
+        let (a, b) = ((step * 17 + 3) % n, (step * 29 + 5) % n);
+        let expected: i64 = naive_path(halide.tree(), a, b).into_iter().map(|x| naive[x]).sum();
+        assert_eq!(halide.query(a, b), Some(expected));
+    }
+}
+
+#[test]
+fn test_update_type_differs_from_value() {
+    let n = 100;
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| i * 7 % 23 - 11).collect();
+    let values = naive.iter().map(|&x| (x, x, 1)).collect();
+    let mut builder = HalideBuilder::with_lazy(values, (Sum, Min, Sum), AddToStats, AddToStats);
+    for (i, p) in random_parents(n, 5).into_iter().enumerate() {
+        builder = builder.add_edge(p, i + 1);
+    }
+    let mut halide = builder.build(0).unwrap();
+
+    for step in 0..50 {
+        let (u, v) = ((step * 41) % n, (step * 17 + 9) % n);
+        let delta = step as i64 % 7 - 3;
+        halide.update(u, v, delta);
+        for x in naive_path(halide.tree(), u, v) {
+            naive[x] += delta;
+        }
+        let w = (step * 13) % n;
+        halide.update_subtree(w, 2);
+        for x in naive_subtree(halide.tree(), n, w) {
+            naive[x] += 2;
+        }
+
+        let (a, b) = ((step * 19 + 4) % n, (step * 31 + 6) % n);
+        let path = naive_path(halide.tree(), a, b);
+        let sum = path.iter().map(|&x| naive[x]).sum();
+        let min = path.iter().map(|&x| naive[x]).min().unwrap();
+        assert_eq!(halide.query(a, b), Some((sum, min, path.len() as i64)));
+    }
+}
+
+#[test]
+fn test_multiply_along_path() {
+    let n = 60;
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| i % 3 + 1).collect();
+    let mut builder = HalideBuilder::with_lazy(naive.clone(), Sum, MulToSum, MulToSum);
+    for (i, p) in random_parents(n, 11).into_iter().enumerate() {
+        builder = builder.add_edge(p, i + 1);
+    }
+    let mut halide = builder.build(0).unwrap();
+
+    for step in 0..20 {
+        let (u, v) = ((step * 13) % n, (step * 31 + 7) % n);
+        let factor = step as i64 % 3 + 1;
+        halide.update(u, v, factor);
+        for x in naive_path(halide.tree(), u, v) {
+            naive[x] *= factor;
+        }
+
+        let (a, b) = ((step * 19 + 1) % n, (step * 23 + 2) % n);
+        let expected: i64 = naive_path(halide.tree(), a, b).into_iter().map(|x| naive[x]).sum();
+        assert_eq!(halide.query(a, b), Some(expected));
+    }
+}
+
+#[test]
+fn test_chmin_along_path() {
+    let n = 100;
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| (i * 37) % 101).collect();
+    let mut builder = HalideBuilder::with_lazy(naive.clone(), Max, ChminToMax, ChminToMax);
+    for (i, p) in random_parents(n, 3).into_iter().enumerate() {
+        builder = builder.add_edge(p, i + 1);
+    }
+    let mut halide = builder.build(0).unwrap();
+
+    for step in 0..50 {
+        let (u, v) = ((step * 41) % n, (step * 7 + 13) % n);
+        let cap = 100 - step as i64;
+        halide.update(u, v, cap);
+        for x in naive_path(halide.tree(), u, v) {
+            naive[x] = naive[x].min(cap);
+        }
+
+        let (a, b) = ((step * 11 + 4) % n, (step * 43 + 9) % n);
+        let expected = naive_path(halide.tree(), a, b).into_iter().map(|x| naive[x]).max().unwrap();
+        assert_eq!(halide.query(a, b), Some(expected));
+        assert_eq!(halide.query_subtree(0), *naive.iter().max().unwrap());
+    }
+}
+
+/// Run a mix of path and subtree updates against both `halide` and a naive
+/// array, checking path and subtree aggregates after every step
+fn check_against_naive<T, C, LA, LF>(
+    halide: &mut Halide<T, C, LA, LF>,
+    naive: &mut [T],
+    updates: &[T],
+    apply: impl Fn(T, T) -> T,
+    fold: impl Fn(Vec<T>) -> T,
+) where
+    T: Clone + PartialEq + Debug,
+    C: CombineFn<T> + Clone,
+    LA: LazyApplyFn<T>,
+    LF: LazyFunc<T>,
+{
+    let n = naive.len();
+    for step in 0..80 {
+        let val = updates[step % updates.len()].clone();
+        let touched = if step % 3 == 0 {
+            let v = (step * 7) % n;
+            halide.update_subtree(v, val.clone());
+            naive_subtree(halide.tree(), n, v)
+        } else {
+            let (u, v) = ((step * 37) % n, (step * 53 + 11) % n);
+            halide.update(u, v, val.clone());
+            naive_path(halide.tree(), u, v)
+        };
+        for x in touched {
+            naive[x] = apply(naive[x].clone(), val.clone());
+        }
+
+        let (a, b) = ((step * 17 + 3) % n, (step * 29 + 5) % n);
+        let expected = fold(naive_path(halide.tree(), a, b).into_iter().map(|x| naive[x].clone()).collect());
+        assert_eq!(halide.query(a, b), Some(expected), "path {} - {} at step {}", a, b, step);
+
+        let v = (step * 13 + 1) % n;
+        let expected = fold(naive_subtree(halide.tree(), n, v).into_iter().map(|x| naive[x].clone()).collect());
+        assert_eq!(halide.query_subtree(v), expected, "subtree {} at step {}", v, step);
+    }
+}
+
+fn random_halide<T, C, LA, LF>(values: Vec<T>, combine: C, lazy_apply: LA, lazy_func: LF) -> Halide<T, C, LA, LF>
+where
+    T: Clone,
+    C: CombineFn<T> + Clone,
+    LA: LazyApplyFn<T>,
+    LF: LazyFunc<T>,
+{
+    let n = values.len();
+    let mut builder = HalideBuilder::with_lazy(values, combine, lazy_apply, lazy_func);
+    for (i, p) in random_parents(n, n as u64).into_iter().enumerate() {
+        builder = builder.add_edge(p, i + 1);
+    }
+    builder.build(0).unwrap()
+}
+
+#[test]
+fn test_range_add_sum() {
+    let mut naive: Vec<i64> = (0..150).map(|i| i % 11 - 5).collect();
+    let mut halide = random_halide(naive.clone(), Sum, RangeAddSum, RangeAddSum);
+    check_against_naive(&mut halide, &mut naive, &[3, -2, 7, 0, -9], |x, d| x + d, |xs| xs.into_iter().sum());
+}
+
+#[test]
+fn test_range_add_min_max() {
+    let mut naive: Vec<i64> = (0..150).map(|i| (i * 31) % 97).collect();
+    let mut halide = random_halide(naive.clone(), Min, RangeAddMinMax, RangeAddMinMax);
+    check_against_naive(&mut halide, &mut naive, &[3, -2, 7, -9], |x, d| x + d, |xs| xs.into_iter().min().unwrap());
+
+    let mut naive: Vec<i64> = (0..150).map(|i| (i * 31) % 97).collect();
+    let mut halide = random_halide(naive.clone(), Max, RangeAddMinMax, RangeAddMinMax);
+    check_against_naive(&mut halide, &mut naive, &[3, -2, 7, -9], |x, d| x + d, |xs| xs.into_iter().max().unwrap());
+}
+
+#[test]
+fn test_range_assign_sum() {
+    let mut naive: Vec<i64> = (0..150).map(|i| i % 13).collect();
+    let mut halide = random_halide(naive.clone(), Sum, RangeAssignSum, RangeAssignSum);
+    check_against_naive(&mut halide, &mut naive, &[4, -1, 10, 0], |_, v| v, |xs| xs.into_iter().sum());
+}
+
+#[test]
+fn test_range_assign_min_max() {
+    let mut naive: Vec<i64> = (0..150).map(|i| (i * 17) % 89).collect();
+    let mut halide = random_halide(naive.clone(), Min, RangeAssignMinMax, RangeAssignMinMax);
+    check_against_naive(&mut halide, &mut naive, &[40, -1, 100, 7], |_, v| v, |xs| xs.into_iter().min().unwrap());
+
+    let mut naive: Vec<i64> = (0..150).map(|i| (i * 17) % 89).collect();
+    let mut halide = random_halide(naive.clone(), Max, RangeAssignMinMax, RangeAssignMinMax);
+    check_against_naive(&mut halide, &mut naive, &[40, -1, 100, 7], |_, v| v, |xs| xs.into_iter().max().unwrap());
+}
+
+#[test]
+fn test_range_affine_sum() {
+    // Values are (sum, count) pairs; updates are (a, b) meaning x -> a * x + b
+    let mut naive: Vec<(i64, i64)> = (0..150).map(|i| (i % 7, 1)).collect();
+    let mut halide = random_halide(naive.clone(), (Sum, Sum), RangeAffineSum, RangeAffineSum);
+    check_against_naive(
+        &mut halide,
+        &mut naive,
+        &[(1, 3), (-1, 0), (1, -5), (2, 1), (-1, 2)],
+        |(x, c), (a, b)| (a * x + b, c),
+        |xs| xs.into_iter().fold((0, 0), |acc, (x, c)| (acc.0 + x, acc.1 + c)),
+    );
+}
+
+#[test]
+fn test_path_find_first() {
+    // Positive values keep the running sum monotone along any path
+    let n = 180;
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| i % 5 + 1).collect();
+    let mut halide = random_halide(naive.clone(), Sum, RangeAddSum, RangeAddSum);
+
+    for step in 0..60 {
+        let (u, v) = ((step * 37) % n, (step * 53 + 11) % n);
+        let delta = step as i64 % 4;
+        halide.update(u, v, delta);
+        for x in naive_path(halide.tree(), u, v) {
+            naive[x] += delta;
+        }
+
+        let (a, b) = ((step * 17 + 3) % n, (step * 29 + 5) % n);
+        let path = naive_path(halide.tree(), a, b);
+        let total: i64 = path.iter().map(|&x| naive[x]).sum();
+        for limit in [0, 3, total / 2, total - 1, total] {
+            let mut running = 0;
+            let expected = path.iter().copied().find(|&x| {
+                running += naive[x];
+                running > limit
+            });
+            assert_eq!(halide.path_find_first(a, b, |&s| s > limit), expected, "{} -> {} over {}", a, b, limit);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use bipath::{HalideBuilder, HalideError, LinkCutTree, PathAggregate, RangeAddSum};
+use bipath::monoids::{Max, Sum};
+use common::{next, AffineCompose};
+
+/// Nodes on the u -> v path of a forest given by adjacency lists, if any
+fn naive_path(adj: &[Vec<usize>], u: usize, v: usize) -> Option<Vec<usize>> {
+    let mut prev = vec![usize::MAX; adj.len()];
+    prev[u] = u;
+    let mut stack = vec![u];
+    while let Some(x) = stack.pop() {
+        for &y in &adj[x] {
+            if prev[y] == usize::MAX {
+                prev[y] = x;
+                stack.push(y);
+            }
+        }
+    }
+    if prev[v] == usize::MAX {
+        return None;
+    }
+
+    let mut path = vec![v];
+    let mut x = v;
+    while x != u {
+        x = prev[x];
+        path.push(x);
+    }
+    path.reverse();
+    Some(path)
+}
+
+#[test]
+fn test_link_cut_connectivity() {
+    let mut lct = LinkCutTree::new(vec![0u64; 6], Sum);
+
+    assert!(!lct.connected(0, 1));
+    lct.link(0, 1).unwrap();
+    lct.link(1, 2).unwrap();
+    lct.link(3, 4).unwrap();
+    assert!(lct.connected(0, 2));
+    assert!(!lct.connected(2, 3));
+
+    lct.link(2, 4).unwrap();
+    assert!(lct.connected(0, 3));
+    lct.cut(1, 2).unwrap();
+    assert!(!lct.connected(0, 3));
+    assert!(lct.connected(2, 3));
+    assert!(lct.connected(5, 5));
+}
+
+#[test]
+fn test_link_cut_errors() {
+    let mut lct = LinkCutTree::new(vec![0u64; 4], Sum);
+    lct.link(0, 1).unwrap();
+    lct.link(1, 2).unwrap();
+
+    assert_eq!(lct.link(2, 0), Err(HalideError::Cycle(2, 0)));
+    assert_eq!(lct.link(3, 3), Err(HalideError::SelfLoop(3)));
+    assert_eq!(lct.link(0, 4), Err(HalideError::InvalidNode { id: 4, n: 4 }));
+    assert_eq!(lct.cut(0, 2), Err(HalideError::MissingEdge(0, 2)));
+    assert_eq!(lct.cut(0, 3), Err(HalideError::MissingEdge(0, 3)));
+    assert_eq!(lct.try_update(0, 3, 1), Err(HalideError::DifferentTrees(0, 3)));
+    assert_eq!(lct.query_path(3, 2), None);
+
+    // Failed operations leave the forest untouched
+    assert!(lct.connected(0, 2));
+    assert_eq!(lct.cut(2, 1), Ok(()));
+}
+
+#[test]
+fn test_query_path_order() {
+    // 0 - 1 - 2 - 3, each node holding an affine map
+    let values = vec![(2u64, 1u64), (3, 0), (1, 5), (2, 0)];
+    let mut lct = LinkCutTree::new(values, AffineCompose);
+    lct.link(1, 0).unwrap();
+    lct.link(2, 3).unwrap();
+    lct.link(1, 2).unwrap();
+
+    assert_eq!(lct.query_path(3, 0), Some((12, 31)));
+    assert_eq!(lct.query_path(0, 3), Some((12, 16)));
+    assert_eq!(lct.query_path(2, 2), Some((1, 5)));
+}
+
+#[test]
+fn test_random_against_naive() {
+    let n = 60;
+    let mut seed = 2024u64;
+    let mut below = |m: usize| next(&mut seed) % m;
+
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| i % 7).collect();
+    let mut adj = vec![Vec::new(); n];
+    let mut lct = LinkCutTree::with_lazy(naive.clone(), Sum, RangeAddSum, RangeAddSum);
+
+    for step in 0..3000 {
+        let (u, v) = (below(n), below(n));
+        match step % 4 {
+            0 => {
+                let linked = lct.link(u, v);
+                if u != v && naive_path(&adj, u, v).is_none() {
+                    assert_eq!(linked, Ok(()));
+                    adj[u].push(v);
+                    adj[v].push(u);
+                } else {
+                    assert!(linked.is_err());
+                }
+            }
+            1 => {
+                // Cut a random existing edge of u, if it has one
+                if !adj[u].is_empty() {
+                    let w = adj[u][below(adj[u].len())];
+                    assert_eq!(lct.cut(w, u), Ok(()));
+                    adj[u].retain(|&x| x != w);
+                    adj[w].retain(|&x| x != u);
+                }
+            }
+            2 => {
+                let delta = below(9) as i64 - 4;
+                if let Some(path) = naive_path(&adj, u, v) {
+                    lct.update(u, v, delta);
+                    for x in path {
+                        naive[x] += delta;
+                    }
+                }
+            }
+            _ => {
+                let expected = naive_path(&adj, u, v).map(|p| p.into_iter().map(|x| naive[x]).sum());
+                assert_eq!(lct.query_path(u, v), expected, "path {} - {} at step {}", u, v, step);
+                assert_eq!(lct.get(u), naive[u]);
+            }
+        }
+    }
+}
+
+/// Heaviest node on each path, written once for any `PathAggregate`
+fn bottlenecks<P: PathAggregate<u32>>(paths: &mut P, pairs: &[(usize, usize)]) -> Vec<Option<u32>> {
+    pairs.iter().map(|&(u, v)| paths.path_query(u, v)).collect()
+}
+
+#[test]
+fn test_same_code_for_both_implementations() {
+    let values = vec![4u32, 9, 1, 7, 3, 8];
+    let edges = [(0, 1), (0, 2), (1, 3), (1, 4), (2, 5)];
+    let pairs = [(3, 5), (4, 4), (2, 0), (3, 4)];
+
+    let mut halide = HalideBuilder::new(values.clone(), Max).edges(edges).build(0).unwrap();
+    let mut lct = LinkCutTree::new(values, Max);
+    for (u, v) in edges {
+        lct.link(u, v).unwrap();
+    }
+
+    assert_eq!(bottlenecks(&mut halide, &pairs), bottlenecks(&mut lct, &pairs));
+    assert_eq!(bottlenecks(&mut lct, &pairs), vec![Some(9), Some(3), Some(4), Some(9)]);
+
+    halide.path_update(4, 5, 2).unwrap();
+    lct.path_update(4, 5, 2).unwrap();
+    assert_eq!(bottlenecks(&mut halide, &pairs), bottlenecks(&mut lct, &pairs));
+    assert!(PathAggregate::connected(&mut halide, 3, 5));
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use bipath::segment_tree::SegmentTree;
+use bipath::monoids::Sum;
+use bipath::{LazyApplyFn, LazyFunc, RangeAddSum, RangeAffineSum};
+use common::{next, MulToSum};
+
+/// Pseudo-random `[l, r]` ranges over `0..n`
+fn ranges(n: usize, count: usize, mut seed: u64) -> Vec<(usize, usize)> {
+    (0..count)
+        .map(|_| {
+            let a = next(&mut seed) % n;
+            let b = next(&mut seed) % n;
+            (a.min(b), a.max(b))
+        })
+        .collect()
+}
+
+#[test]
+fn test_first_add_is_not_doubled() {
+    let mut seg = SegmentTree::from_slice(&[1i64, 2, 3, 4], Sum, RangeAddSum, RangeAddSum);
+
+    seg.update(0, 3, 5);
+    assert_eq!(seg.query(0, 3), 10 + 4 * 5);
+    seg.update(1, 1, 5);
+    assert_eq!(seg.query(1, 1), 2 + 10);
+    assert_eq!(seg.query(0, 3), 10 + 5 * 5);
+}
+
+#[test]
+fn test_range_add_against_naive() {
+    let n = 57;
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| i * 3 % 17 - 8).collect();
+    let mut seg = SegmentTree::from_slice(&naive, Sum, RangeAddSum, RangeAddSum);
+
+    for (step, (l, r)) in ranges(n, 200, 1).into_iter().enumerate() {
+        let delta = step as i64 % 11 - 5;
+        seg.update(l, r, delta);
+        for x in &mut naive[l..=r] {
+            *x += delta;
+        }
+
+        let (a, b) = ranges(n, 1, step as u64)[0];
+        assert_eq!(seg.query(a, b), naive[a..=b].iter().sum::<i64>());
+        assert_eq!(seg.query(a, a), naive[a]);
+    }
+}
+
+#[test]
+fn test_range_multiply_against_naive() {
+    let n = 33;
+    let mut naive: Vec<i64> = (0..n as i64).map(|i| i % 5 - 2).collect();
+    let mut seg = SegmentTree::from_slice(&naive, Sum, MulToSum, MulToSum);
+
+    for (step, (l, r)) in ranges(n, 40, 2).into_iter().enumerate() {
+        let factor = [2, -1, 3, 1][step % 4];
+        seg.update(l, r, factor);
+        for x in &mut naive[l..=r] {
+            *x *= factor;
+        }
+
+        let (a, b) = ranges(n, 1, step as u64 + 100)[0];
+        assert_eq!(seg.query(a, b), naive[a..=b].iter().sum::<i64>());
+    }
+}
+
+#[test]
+fn test_range_affine_against_naive() {
+    let n = 41;
+    let mut naive: Vec<u64> = (0..n as u64).map(|i| i * i % 97).collect();
+    let values: Vec<(u64, u64)> = naive.iter().map(|&x| (x, 1)).collect();
+    let mut seg = SegmentTree::from_slice(&values, (Sum, Sum), RangeAffineSum, RangeAffineSum);
+
+    for (step, (l, r)) in ranges(n, 30, 3).into_iter().enumerate() {
+        // Small maps and periodic resets keep every sum far below overflow
+        let (a, b) = (step as u64 % 3 + 1, step as u64 % 7);
+        seg.update(l, r, (a, b));
+        for x in &mut naive[l..=r] {
+            *x = a * *x + b;
+        }
+        if step % 10 == 9 {
+            // x -> 0 * x + 1 assigns through the same action
+            seg.update(0, n - 1, (0, 1));
+            naive.iter_mut().for_each(|x| *x = 1);
+        }
+
+        let (qa, qb) = ranges(n, 1, step as u64 + 200)[0];
+        assert_eq!(seg.query(qa, qb), (naive[qa..=qb].iter().sum::<u64>(), (qb - qa + 1) as u64));
+        assert_eq!(seg.query_rev(qa, qb).0, naive[qa..=qb].iter().sum::<u64>());
+    }
+}
+
+/// An integer that is `Clone` but not `Copy`, like a bignum
+#[derive(Clone, Debug, PartialEq)]
+struct Big(Box<i64>);
+
+impl std::ops::Add for Big {
+    type Output = Big;
+    fn add(self, other: Big) -> Big {
+        Big(Box::new(*self.0 + *other.0))
+    }
+}
+
+impl std::ops::Mul for Big {
+    type Output = Big;
+    fn mul(self, other: Big) -> Big {
+        Big(Box::new(*self.0 * *other.0))
+    }
+}
+
+#[test]
+fn test_range_affine_without_copy() {
+    let big = |x| Big(Box::new(x));
+
+    // x -> 3 * (2 * x + 1) + 4
+    let composed = LazyApplyFn::apply(&RangeAffineSum, &(big(2), big(1)), &(big(3), big(4)));
+    assert_eq!(composed, (big(6), big(7)));
+
+    // Three elements summing to 10
+    let applied = LazyFunc::apply(&RangeAffineSum, &(big(10), big(3)), &composed, 0, 2);
+    assert_eq!(applied, (big(6 * 10 + 7 * 3), big(3)));
+}